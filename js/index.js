@@ -1 +1 @@
-import("../pkg/index.js").catch(console.error);
+import("../pkg/index.js").then(slime => slime.main_js()).catch(console.error);
@@ -0,0 +1,346 @@
+use std::cell::{ Cell, Ref, RefCell };
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use game_loop::game_loop;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::brush::{ Brush, Tool };
+use crate::palette::{ parse_color, Colormap, Intensity, Palette };
+use crate::params::SimParams;
+use crate::sim::Dish;
+use crate::vec2d::{ Resize, Vec2d };
+use crate::web::WebDish;
+
+/// What `new Simulation(options)` takes; every field is optional.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    /// Random if unset.
+    pub seed: Option<u64>,
+    /// Id of a `<canvas>` to draw on. The world then follows its on-screen size.
+    pub canvas: Option<String>,
+    /// CSS pixels per trail cell on the canvas.
+    pub cell_size: f64,
+    /// How the trail is carried over when the canvas changes size: "crop", "nearest" or "bilinear".
+    pub resize: Resize,
+    /// Starting parameters by name: numbers as `set_param` takes them, strings as `set_param_str`.
+    pub params: BTreeMap<String, serde_json::Value>,
+    /// How the canvas colors the trail; see `set_palette`.
+    pub palette: PaletteOptions,
+    pub renderer: Renderer,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Options { seed: None, canvas: None, cell_size: 2., resize: Resize::Bilinear, params: BTreeMap::new(),
+                  palette: PaletteOptions::default(), renderer: Renderer::Canvas2d }
+    }
+}
+
+/// A `Palette` as JS spells it, e.g. `{ colormap: "magma", gamma: 0.5 }`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaletteOptions {
+    /// gray, viridis, magma, inferno or a comma-separated list of `#rrggbb` colors.
+    pub colormap: Option<String>,
+    pub gamma: Option<f64>,
+    pub log: bool,
+    /// `#rrggbb` per species, blended instead of using the colormap.
+    pub species_colors: Vec<String>,
+}
+impl PaletteOptions {
+    pub fn to_palette(&self) -> Result<Palette, String> {
+        let mut palette = Palette::default();
+        if let Some(colormap) = &self.colormap {
+            palette.colormap = Colormap::parse(colormap)?;
+        }
+        palette.intensity = match (self.gamma, self.log) {
+            (Some(_), true) => return Err(String::from("pick one of gamma and log")),
+            (Some(gamma), false) => Intensity::Gamma(gamma),
+            (None, true) => Intensity::Log,
+            (None, false) => Intensity::Linear,
+        };
+        palette.species_colors = self.species_colors.iter().map(|c| parse_color(c)).collect::<Result<_, _>>()?;
+        palette.validate()?;
+        Ok(palette)
+    }
+}
+
+/// What draws on the canvas. A canvas only ever gets one kind of context, so this is fixed
+/// when the simulation is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Renderer {
+    /// `CanvasRenderingContext2d`, one `put_image_data` per frame.
+    #[serde(rename = "2d")]
+    Canvas2d,
+    /// `GlRenderer`: the colormap is applied on the GPU.
+    #[serde(rename = "webgl")]
+    WebGl,
+}
+
+struct State {
+    dish: Dish,
+    updates: u32,
+    paused: bool,
+    running: bool,
+    view: Option<WebDish>,
+    renderer: Renderer,
+    palette: Palette,
+    cell_size: f64,
+    resize: Resize,
+    trail: Vec2d<u8>,   // backs `trail_view`
+    brush: Option<Brush>,
+    stroke: Option<(f64, f64)>, // last painted point while the pointer is down
+}
+impl State {
+    fn step(&mut self) {
+        self.dish.update(self.updates);
+        self.updates += 1;
+    }
+}
+
+/// A simulation for JavaScript to drive: step it by hand, or `start` it to run and draw on its
+/// canvas every animation frame until paused.
+#[wasm_bindgen]
+pub struct Simulation {
+    state: Rc<RefCell<State>>,  // shared with the loop once started
+}
+#[wasm_bindgen]
+impl Simulation {
+    /// `options` is an `Options` object, or undefined for the defaults.
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: JsValue) -> Result<Simulation, String> {
+        if options.is_undefined() || options.is_null() {
+            return Simulation::with_options(Options::default());
+        }
+        let json = js_sys::JSON::stringify(&options).map_err(|_| String::from("options must be plain data"))?;
+        Simulation::from_json(&String::from(json))
+    }
+    /// Like `new`, with the options as a JSON string.
+    pub fn from_json(options: &str) -> Result<Simulation, String> {
+        Simulation::with_options(serde_json::from_str(options).map_err(|e| format!("bad options: {}", e))?)
+    }
+    /// Advance `n` updates right away, paused or not.
+    pub fn step(&mut self, n: u32) {
+        let mut state = self.state.borrow_mut();
+        for _ in 0..n {
+            state.step();
+        }
+    }
+    /// Start over from the spawn pattern, with the current parameters.
+    pub fn reset(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.dish.reset();
+        state.updates = 0;
+    }
+    /// Set a numeric parameter by name, as in `SimParams::set`. The world size can't change.
+    pub fn set_param(&mut self, name: &str, value: f64) -> Result<(), String> {
+        self.update_params(|params| params.set(name, value))
+    }
+    /// Set a parameter by name from a string, as in `SimParams::set_str`, e.g. `boundary`.
+    pub fn set_param_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.update_params(|params| params.set_str(name, value))
+    }
+    /// Stop updating in the running loop; the canvas keeps showing the last state.
+    pub fn pause(&mut self) {
+        self.state.borrow_mut().paused = true;
+    }
+    pub fn resume(&mut self) {
+        self.state.borrow_mut().paused = false;
+    }
+    pub fn paused(&self) -> bool {
+        self.state.borrow().paused
+    }
+    /// Updates since the start or the last `reset`.
+    pub fn updates(&self) -> u32 {
+        self.state.borrow().updates
+    }
+    pub fn width(&self) -> usize {
+        self.state.borrow().dish.size_w
+    }
+    pub fn height(&self) -> usize {
+        self.state.borrow().dish.size_h
+    }
+    /// Color the trail from the next frame on. `palette` is a `PaletteOptions` object, e.g.
+    /// `{ colormap: "viridis", log: true }` or `{ species_colors: ["#ff0000", "#00ffff"] }`.
+    pub fn set_palette(&mut self, palette: JsValue) -> Result<(), String> {
+        let json = js_sys::JSON::stringify(&palette).map_err(|_| String::from("palette must be plain data"))?;
+        self.set_palette_json(&String::from(json))
+    }
+    /// Like `set_palette`, with the palette as a JSON string.
+    pub fn set_palette_json(&mut self, palette: &str) -> Result<(), String> {
+        let palette: PaletteOptions = serde_json::from_str(palette).map_err(|e| format!("bad palette: {}", e))?;
+        let palette = palette.to_palette()?;
+        let state = &mut *self.state.borrow_mut();
+        if let Some(view) = &mut state.view {
+            view.set_palette(palette.clone())?;
+        }
+        state.palette = palette;
+        Ok(())
+    }
+    /// Paint with the pointer on the canvas once started: `tool` is trail, nutrient, repellent,
+    /// wall or agents, see `Brush`. With `species` set, trail and agents are only that species'.
+    pub fn set_brush(&mut self, tool: &str, radius: f64, strength: f64, erase: bool, species: Option<usize>) -> Result<(), String> {
+        let brush = Brush { tool: Tool::parse(tool)?, radius, strength, erase, species };
+        let mut state = self.state.borrow_mut();
+        brush.validate(&state.dish.params)?;
+        state.brush = Some(brush);
+        Ok(())
+    }
+    /// Stop painting with the pointer.
+    pub fn clear_brush(&mut self) {
+        self.state.borrow_mut().brush = None;
+    }
+    /// Dab the brush at world coordinates (`x`, `y`), e.g. for painting from your own events.
+    pub fn paint(&mut self, x: f64, y: f64) -> Result<(), String> {
+        let state = &mut *self.state.borrow_mut();
+        let brush = state.brush.as_ref().ok_or("no brush set")?;
+        state.dish.paint(brush, x, y)
+    }
+    /// The total trail, one byte per cell row by row, as a view straight into wasm memory.
+    ///
+    /// The view is invalidated whenever wasm memory grows, which the next `step`, `reset`,
+    /// `paint` or `trail_view` (or any frame of a started loop) may do: its buffer is detached
+    /// and reads come back empty. Do not hold it across calls into wasm; read it straight away,
+    /// or `.slice()` it to keep a copy.
+    pub fn trail_view(&mut self) -> js_sys::Uint8Array {
+        let mut state = self.state.borrow_mut();
+        state.trail = state.dish.total_trail();
+        unsafe { js_sys::Uint8Array::view(state.trail.as_slice()) }
+    }
+    /// Run `updates` at 40 per second and, with a canvas, draw every animation frame and
+    /// follow the window's size and paint with the brush under the mouse or a finger. Pause
+    /// rather than free a started simulation; the loop keeps it.
+    pub fn start(&mut self) -> Result<(), String> {
+        if self.state.borrow().running {
+            return Err(String::from("already started"));
+        }
+        self.state.borrow_mut().running = true;
+        // resize events just flag it for the next update
+        let resized = Rc::new(Cell::new(true));
+        if self.state.borrow().view.is_some() {
+            let window = web_sys::window().ok_or("no window")?;
+            let on_resize = {
+                let resized = resized.clone();
+                Closure::<dyn FnMut()>::new(move || resized.set(true))
+            };
+            window.add_event_listener_with_callback("resize", on_resize.as_ref().unchecked_ref())
+                .map_err(|_| String::from("couldn't listen for resizes"))?;
+            on_resize.forget();
+            self.listen_for_pointer()?;
+        }
+        game_loop(self.state.clone(), 40, 0.02, move |g| {
+            // update fn
+            let state = &mut *g.game.borrow_mut();
+            if let (true, Some(view)) = (resized.replace(false), &mut state.view) {
+                if let Err(e) = view.fit_canvas(&mut state.dish, state.cell_size, state.resize) {
+                    web_sys::console::error_1(&JsValue::from_str(&format!("couldn't resize: {}", e)));
+                }
+            }
+            if !state.paused {
+                state.step();
+            }
+        }, |g| {
+            // render fn
+            let state = &mut *g.game.borrow_mut();
+            let Some(view) = &mut state.view else { return };
+            match state.renderer {
+                Renderer::Canvas2d => view.render(&state.dish),
+                Renderer::WebGl => if let Err(e) = view.render_webgl(&state.dish) {
+                    web_sys::console::error_1(&JsValue::from_str(&format!("couldn't draw: {}", e)));
+                    // don't flood the console every frame
+                    state.paused = true;
+                    state.view = None;
+                }
+            }
+        });
+        Ok(())
+    }
+}
+impl Simulation {
+    pub fn with_options(options: Options) -> Result<Simulation, String> {
+        if !(options.cell_size.is_finite() && options.cell_size > 0.) {
+            return Err(format!("cell_size must be positive, got {}", options.cell_size));
+        }
+        let mut params = SimParams::default();
+        for (name, value) in &options.params {
+            match value {
+                serde_json::Value::Number(n) => params.set(name, n.as_f64().unwrap_or(f64::NAN))?,
+                serde_json::Value::String(s) => params.set_str(name, s)?,
+                v => return Err(format!("`{}` must be a number or a string, got {}", name, v)),
+            }
+        }
+        let palette = options.palette.to_palette()?;
+        let mut dish = Dish::new(params, options.seed.unwrap_or_else(rand::random))?;
+        let mut view = options.canvas.as_deref().map(WebDish::by_id).transpose()?;
+        if let Some(view) = &mut view {
+            view.fit_canvas(&mut dish, options.cell_size, options.resize)?;
+            view.set_palette(palette.clone())?;
+        }
+        let state = State { dish, updates: 0, paused: false, running: false, view, renderer: options.renderer, palette,
+                            cell_size: options.cell_size, resize: options.resize, trail: Vec2d::new(0, 0, 0),
+                            brush: None, stroke: None };
+        Ok(Simulation { state: Rc::new(RefCell::new(state)) })
+    }
+    /// The simulation itself, for Rust callers.
+    pub fn dish(&self) -> Ref<'_, Dish> {
+        Ref::map(self.state.borrow(), |state| &state.dish)
+    }
+    pub fn palette(&self) -> Ref<'_, Palette> {
+        Ref::map(self.state.borrow(), |state| &state.palette)
+    }
+    pub fn renderer(&self) -> Renderer {
+        self.state.borrow().renderer
+    }
+    /// How the world follows the canvas' size, see `Options::resize`.
+    pub fn resize_mode(&self) -> Resize {
+        self.state.borrow().resize
+    }
+    /// Pointer events on the canvas cover the mouse, touch and pens alike: pressing paints a
+    /// dab and dragging paints a line from the last one.
+    fn listen_for_pointer(&self) -> Result<(), String> {
+        let on_pointer = {
+            let state = self.state.clone();
+            Closure::<dyn FnMut(web_sys::PointerEvent)>::new(move |event: web_sys::PointerEvent| {
+                let state = &mut *state.borrow_mut();
+                let kind = event.type_();
+                if kind == "pointerup" || kind == "pointercancel" {
+                    state.stroke = None;
+                    return;
+                }
+                let (Some(view), Some(brush)) = (&state.view, &state.brush) else { return };
+                let at = view.cell_at(&state.dish, event.offset_x() as f64, event.offset_y() as f64);
+                let painted = match (kind.as_str(), state.stroke) {
+                    ("pointerdown", _) => {
+                        // keep getting moves while dragging off the canvas
+                        let _ = view.canvas().set_pointer_capture(event.pointer_id());
+                        state.dish.paint(brush, at.0, at.1)
+                    }
+                    (_, Some(last)) => state.dish.paint_line(brush, last, at),
+                    (_, None) => return,
+                };
+                event.prevent_default();
+                state.stroke = Some(at);
+                if let Err(e) = painted {
+                    web_sys::console::error_1(&JsValue::from_str(&format!("couldn't paint: {}", e)));
+                }
+            })
+        };
+        let state = self.state.borrow();
+        let canvas = state.view.as_ref().expect("checked by start").canvas();
+        for kind in ["pointerdown", "pointermove", "pointerup", "pointercancel"] {
+            canvas.add_event_listener_with_callback(kind, on_pointer.as_ref().unchecked_ref())
+                .map_err(|_| format!("couldn't listen for {}", kind))?;
+        }
+        on_pointer.forget();
+        Ok(())
+    }
+    fn update_params(&mut self, set: impl FnOnce(&mut SimParams) -> Result<(), String>) -> Result<(), String> {
+        let mut state = self.state.borrow_mut();
+        let mut params = state.dish.params.clone();
+        set(&mut params)?;
+        state.dish.set_params(params)
+    }
+}
@@ -0,0 +1,179 @@
+// Runs the simulation without a browser and writes the trail map to disk every few steps.
+//
+//   slime-headless [--steps N] [--every K] [--seed S] [--format png|ppm] [--trail u8|u16|f32]
+//                  [--walls PNG] [--nutrient PNG] [--repellent PNG] [--spawn-image PNG]
+//                  [--food X,Y[,STRENGTH[,RADIUS]] ...] [--hazard X,Y[,STRENGTH[,RADIUS]] ...]
+//                  [--palette NAME|#RRGGBB,...] [--gamma G | --log] [--species-colors #RRGGBB,...]
+//                  [--out DIR] [param=value ...]
+//
+// e.g. `slime-headless --steps 500 --every 50 --out frames sensor_angle=0.5 deposit_mode=bilinear`
+//
+// `--walls` takes an image the size of the world; its dark pixels (below 128) become walls.
+// `--nutrient` takes one too; its pixel values are added to what agents sense, times each
+// species' `nutrient_weight`. `--repellent` paints an initial repellent field the same way,
+// which then spreads and fades like the trail. `--food` places a source injecting trail every
+// step and `--hazard` one injecting repellent; both can be repeated. `--spawn-image` places
+// agents with a density following the image's brightness.
+//
+// Frames are grayscale unless a palette is given: `--palette` takes viridis, magma, inferno or
+// a list of colors to blend between, `--gamma`/`--log` brighten faint trail, and
+// `--species-colors` gives each species its own color instead.
+
+use std::env;
+use std::fs::{ self, File };
+use std::io::{ BufWriter, Write };
+use std::path::PathBuf;
+use std::process::exit;
+
+use rust_webpack_template::export::{ read_png, write_png, write_png_frame, write_ppm, write_ppm_frame };
+use rust_webpack_template::palette::{ parse_color, Colormap, Intensity, Palette };
+use rust_webpack_template::params::SimParams;
+use rust_webpack_template::render::Frame;
+use rust_webpack_template::sim::{ Dish, Source };
+use rust_webpack_template::spawn::SpawnPattern;
+use rust_webpack_template::trail::TrailValue;
+
+struct Args {
+    steps: u32,
+    every: u32,
+    seed: u64,
+    png: bool,
+    trail: String,
+    walls: Option<PathBuf>,
+    nutrient: Option<PathBuf>,
+    repellent: Option<PathBuf>,
+    spawn_image: Option<PathBuf>,
+    sources: Vec<Source>,
+    palette: Palette,
+    out: PathBuf,
+    params: SimParams,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        steps: 1000,
+        every: 100,
+        seed: 0,
+        png: true,
+        trail: String::from("u8"),
+        walls: None,
+        nutrient: None,
+        repellent: None,
+        spawn_image: None,
+        sources: Vec::new(),
+        palette: Palette::default(),
+        out: PathBuf::from("frames"),
+        params: SimParams::default(),
+    };
+    let mut it = env::args().skip(1);
+    while let Some(arg) = it.next() {
+        let mut val = || it.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--steps"  => args.steps = val()?.parse().map_err(|e| format!("--steps: {}", e))?,
+            "--every"  => args.every = val()?.parse().map_err(|e| format!("--every: {}", e))?,
+            "--seed"   => args.seed = val()?.parse().map_err(|e| format!("--seed: {}", e))?,
+            "--out"    => args.out = PathBuf::from(val()?),
+            "--trail"  => args.trail = val()?,
+            "--walls"  => args.walls = Some(PathBuf::from(val()?)),
+            "--nutrient" => args.nutrient = Some(PathBuf::from(val()?)),
+            "--repellent" => args.repellent = Some(PathBuf::from(val()?)),
+            "--spawn-image" => args.spawn_image = Some(PathBuf::from(val()?)),
+            "--palette" => args.palette.colormap = Colormap::parse(&val()?)?,
+            "--gamma"  => args.palette.intensity = Intensity::Gamma(val()?.parse().map_err(|e| format!("--gamma: {}", e))?),
+            "--log"    => args.palette.intensity = Intensity::Log,
+            "--species-colors" => args.palette.species_colors = val()?.split(',').map(parse_color).collect::<Result<_, _>>()?,
+            "--food"   => args.sources.push(parse_source(&arg, &val()?)?),
+            "--hazard" => args.sources.push(parse_source(&arg, &val()?)?),
+            "--format" => args.png = match val()?.as_str() {
+                "png" => true,
+                "ppm" => false,
+                f => return Err(format!("unknown format `{}`", f)),
+            },
+            _ => {
+                let (name, value) = arg.split_once('=').ok_or_else(|| format!("unexpected argument `{}`", arg))?;
+                args.params.set_str(name, value)?;
+            }
+        }
+    }
+    args.palette.validate()?;
+    if args.every == 0 {
+        return Err(String::from("--every must be at least 1"));
+    }
+    Ok(args)
+}
+
+/// `--food` or `--hazard` X,Y[,STRENGTH[,RADIUS]]: a source of trail or of repellent.
+fn parse_source(flag: &str, spec: &str) -> Result<Source, String> {
+    let nums = spec.split(',').map(|v| v.trim().parse::<f64>().map_err(|e| format!("{} `{}`: {}", flag, spec, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut source = Source::default();
+    let strength = match nums[..] {
+        [x, y]            => { source.pos_x = x; source.pos_y = y; source.strength }
+        [x, y, s]         => { source.pos_x = x; source.pos_y = y; s }
+        [x, y, s, r]      => { source.pos_x = x; source.pos_y = y; source.radius = r; s }
+        _ => return Err(format!("{} `{}`: expected X,Y[,STRENGTH[,RADIUS]]", flag, spec)),
+    };
+    if flag == "--hazard" {
+        source.strength = 0.;
+        source.repellent = strength;
+    } else {
+        source.strength = strength;
+    }
+    source.validate()?;
+    Ok(source)
+}
+
+fn run(args: Args) -> Result<(), String> {
+    match args.trail.as_str() {
+        "u8"  => run_with::<u8>(args),
+        "u16" => run_with::<u16>(args),
+        "f32" => run_with::<f32>(args),
+        t => Err(format!("unknown trail type `{}`", t)),
+    }
+}
+
+fn run_with<T: TrailValue>(mut args: Args) -> Result<(), String> {
+    fs::create_dir_all(&args.out).map_err(|e| format!("{}: {}", args.out.display(), e))?;
+    if let Some(path) = &args.spawn_image {
+        let image = File::open(path).and_then(read_png).map_err(|e| format!("{}: {}", path.display(), e))?;
+        args.params.spawn = SpawnPattern::Image(image.map(|&v| v as f32));
+    }
+    let mut dish = Dish::<T>::with_trail(args.params, args.seed)?;
+    if let Some(path) = &args.walls {
+        let image = File::open(path).and_then(read_png).map_err(|e| format!("{}: {}", path.display(), e))?;
+        dish.set_walls(Some(image.map(|&v| v < 128))).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    if let Some(path) = &args.nutrient {
+        let image = File::open(path).and_then(read_png).map_err(|e| format!("{}: {}", path.display(), e))?;
+        dish.set_nutrient(Some(image.map(|&v| v as f32))).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    if let Some(path) = &args.repellent {
+        let image = File::open(path).and_then(read_png).map_err(|e| format!("{}: {}", path.display(), e))?;
+        dish.set_repellent(Some(image.map(|&v| v as f32))).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    dish.sources = args.sources;
+    // plain gray frames stay single-channel PNGs
+    let gray = args.palette == Palette::default();
+    let mut frame = Frame::with_palette(args.palette);
+    for step in 1..=args.steps {
+        dish.update(step);
+        if step % args.every == 0 {
+            let path = args.out.join(format!("frame_{:06}.{}", step, if args.png { "png" } else { "ppm" }));
+            let mut out = BufWriter::new(File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?);
+            if gray {
+                if args.png { write_png(&mut out, &dish.total_trail()) } else { write_ppm(&mut out, &dish.total_trail()) }
+            } else {
+                frame.draw(&dish);
+                if args.png { write_png_frame(&mut out, &frame) } else { write_ppm_frame(&mut out, &frame) }
+            }.and_then(|()| out.flush()).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = parse_args().and_then(run) {
+        eprintln!("slime-headless: {}", e);
+        exit(1);
+    }
+}
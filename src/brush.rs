@@ -0,0 +1,71 @@
+use crate::params::SimParams;
+
+/// What a `Brush` paints onto a dish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// Trail, as if agents had deposited it.
+    Trail,
+    /// The static nutrient field agents are drawn to.
+    Nutrient,
+    /// The repellent field, which then spreads and fades like the trail.
+    Repellent,
+    /// Walls, removing any agents they're painted over; `strength` is ignored.
+    Wall,
+    /// `strength` new agents per dab, scattered over the brush; erasing removes every agent under it.
+    Agents,
+}
+impl Tool {
+    /// trail, nutrient, repellent, wall or agents.
+    pub fn parse(name: &str) -> Result<Tool, String> {
+        Ok(match name {
+            "trail"     => Tool::Trail,
+            "nutrient"  => Tool::Nutrient,
+            "repellent" => Tool::Repellent,
+            "wall"      => Tool::Wall,
+            "agents"    => Tool::Agents,
+            _ => return Err(format!("unknown brush tool `{}`", name)),
+        })
+    }
+}
+
+/// Most agents an `Agents` brush may add per dab, so a slip of the slider can't hang the page.
+pub const MAX_AGENTS_PER_DAB: f64 = 10_000.;
+
+/// A round brush for `Dish::paint`: adds `strength` of `tool` to every cell within `radius`
+/// of where it's dabbed, or takes it away (clearing at zero) when `erase` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Brush {
+    pub tool: Tool,
+    pub radius: f64,
+    pub strength: f64,
+    pub erase: bool,
+    /// For `Trail` and `Agents`: only this species, rather than every species for trail and a
+    /// random one for each new agent.
+    pub species: Option<usize>,
+}
+impl Default for Brush {
+    fn default() -> Self {
+        Brush { tool: Tool::Trail, radius: 4., strength: 100., erase: false, species: None }
+    }
+}
+impl Brush {
+    /// Checks the brush against the world it's about to paint, as set up by `params`.
+    pub fn validate(&self, params: &SimParams) -> Result<(), String> {
+        if !(self.radius.is_finite() && self.radius >= 0. && self.strength.is_finite() && self.strength >= 0.) {
+            return Err(format!("brush radius and strength must be non-negative, got {} and {}", self.radius, self.strength));
+        }
+        // any bigger covers no more of the world, only takes longer
+        let diagonal = (params.size_w as f64).hypot(params.size_h as f64);
+        if self.radius > diagonal {
+            return Err(format!("brush radius {} is larger than the world's diagonal, {:.0}", self.radius, diagonal));
+        }
+        if self.tool == Tool::Agents && self.strength > MAX_AGENTS_PER_DAB {
+            return Err(format!("an agents brush adds at most {} per dab, got {}", MAX_AGENTS_PER_DAB, self.strength));
+        }
+        let species = params.species.len();
+        match self.species {
+            Some(s) if s >= species => Err(format!("brush species {} out of range, there are {}", s, species)),
+            _ => Ok(()),
+        }
+    }
+}
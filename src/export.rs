@@ -0,0 +1,62 @@
+use std::io::{ self, Write };
+
+use crate::render::Frame;
+use crate::vec2d::Vec2d;
+
+/// Binary PPM (P6), grayscale trail replicated into RGB.
+pub fn write_ppm<W: Write>(out: &mut W, trail: &Vec2d<u8>) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", trail.size_w, trail.size_h)?;
+    let rgb: Vec<u8> = trail.as_slice().iter().flat_map(|&v| [v, v, v]).collect();
+    out.write_all(&rgb)
+}
+
+/// Binary PPM (P6) of a colored frame, alpha dropped.
+pub fn write_ppm_frame<W: Write>(out: &mut W, frame: &Frame) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", frame.size_w, frame.size_h)?;
+    let rgb: Vec<u8> = frame.pixels().chunks(4).flat_map(|px| [px[0], px[1], px[2]]).collect();
+    out.write_all(&rgb)
+}
+
+/// 8-bit grayscale PNG of the trail.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_png<W: Write>(out: &mut W, trail: &Vec2d<u8>) -> io::Result<()> {
+    let mut enc = png::Encoder::new(out, trail.size_w as u32, trail.size_h as u32);
+    enc.set_color(png::ColorType::Grayscale);
+    enc.set_depth(png::BitDepth::Eight);
+    let mut writer = enc.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(trail.as_slice()).map_err(io::Error::other)?;
+    // writes IEND; dropping the writer would too, but without reporting errors
+    writer.finish().map_err(io::Error::other)
+}
+
+/// 8-bit RGBA PNG of a colored frame.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_png_frame<W: Write>(out: &mut W, frame: &Frame) -> io::Result<()> {
+    let mut enc = png::Encoder::new(out, frame.size_w as u32, frame.size_h as u32);
+    enc.set_color(png::ColorType::Rgba);
+    enc.set_depth(png::BitDepth::Eight);
+    let mut writer = enc.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(frame.pixels()).map_err(io::Error::other)?;
+    // writes IEND; dropping the writer would too, but without reporting errors
+    writer.finish().map_err(io::Error::other)
+}
+
+/// Reads any 8-bit-or-less PNG as grayscale (mean of RGB, alpha ignored), e.g. to load walls.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_png<R: io::Read>(input: R) -> io::Result<Vec2d<u8>> {
+    let mut dec = png::Decoder::new(input);
+    dec.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = dec.read_info().map_err(io::Error::other)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(io::Error::other)?;
+    let channels = info.color_type.samples();
+    let mut out = Vec2d::new(info.width as usize, info.height as usize, 0u8);
+    for (o, px) in out.as_mut_slice().iter_mut().zip(buf[..info.buffer_size()].chunks(channels)) {
+        *o = match px {
+            [v] | [v, _] => *v,
+            [r, g, b, ..] => ((*r as u16 + *g as u16 + *b as u16) / 3) as u8,
+            _ => unreachable!(),
+        };
+    }
+    Ok(out)
+}
@@ -0,0 +1,233 @@
+use std::convert::TryFrom;
+
+use web_sys::WebGlRenderingContext as GLC;
+
+use crate::palette::{ cell_value, Palette };
+use crate::render::Frame;
+use crate::sim::Dish;
+use crate::trail::TrailValue;
+
+/// The slice of the WebGL 1 API `GlRenderer` uses, with the same names and arguments as
+/// `web_sys::WebGlRenderingContext` (implemented in `web`), so a mock can stand in for it in
+/// native tests. Constants are `WebGlRenderingContext`'s.
+pub trait Gl {
+    type Shader;
+    type Program;
+    type Buffer;
+    type Texture;
+    type Location;
+
+    fn create_shader(&self, kind: u32) -> Option<Self::Shader>;
+    fn shader_source(&self, shader: &Self::Shader, source: &str);
+    fn compile_shader(&self, shader: &Self::Shader);
+    /// `COMPILE_STATUS`
+    fn shader_compiled(&self, shader: &Self::Shader) -> bool;
+    fn shader_info_log(&self, shader: &Self::Shader) -> Option<String>;
+
+    fn create_program(&self) -> Option<Self::Program>;
+    fn attach_shader(&self, program: &Self::Program, shader: &Self::Shader);
+    fn link_program(&self, program: &Self::Program);
+    /// `LINK_STATUS`
+    fn program_linked(&self, program: &Self::Program) -> bool;
+    fn program_info_log(&self, program: &Self::Program) -> Option<String>;
+    fn use_program(&self, program: Option<&Self::Program>);
+    fn get_attrib_location(&self, program: &Self::Program, name: &str) -> i32;
+    fn get_uniform_location(&self, program: &Self::Program, name: &str) -> Option<Self::Location>;
+    fn uniform1i(&self, location: Option<&Self::Location>, x: i32);
+
+    fn create_buffer(&self) -> Option<Self::Buffer>;
+    fn bind_buffer(&self, target: u32, buffer: Option<&Self::Buffer>);
+    fn buffer_data_f32(&self, target: u32, data: &[f32], usage: u32);
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, kind: u32, normalized: bool, stride: i32, offset: i32);
+    fn enable_vertex_attrib_array(&self, index: u32);
+
+    fn create_texture(&self) -> Option<Self::Texture>;
+    fn active_texture(&self, unit: u32);
+    fn bind_texture(&self, target: u32, texture: Option<&Self::Texture>);
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32);
+    fn pixel_storei(&self, pname: u32, param: i32);
+    #[allow(clippy::too_many_arguments)]
+    fn tex_image_2d(&self, target: u32, level: i32, internal_format: i32, width: i32, height: i32,
+                    format: u32, kind: u32, pixels: &[u8]) -> Result<(), String>;
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32);
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32);
+    fn clear(&self, mask: u32);
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32);
+}
+
+pub const VERTEX_SHADER: &str = r#"
+attribute vec2 a_position;
+varying vec2 v_uv;
+
+void main() {
+    // row 0 of the trail map is the top of the screen
+    v_uv = vec2(a_position.x * 0.5 + 0.5, 0.5 - a_position.y * 0.5);
+    gl_Position = vec4(a_position, 0.0, 1.0);
+}
+"#;
+
+pub const FRAGMENT_SHADER: &str = r#"
+precision mediump float;
+
+varying vec2 v_uv;
+uniform sampler2D u_trail;      // LUMINANCE, one texel per cell
+uniform sampler2D u_colormap;   // 256x1 RGBA lookup table
+uniform bool u_direct;          // u_trail is RGBA and already colored, e.g. blended per species
+
+void main() {
+    vec4 cell = texture2D(u_trail, v_uv);
+    gl_FragColor = u_direct ? cell : texture2D(u_colormap, vec2(cell.r * (255.0 / 256.0) + 0.5 / 256.0, 0.5));
+}
+"#;
+
+/// Two triangles covering the viewport, as a `TRIANGLE_STRIP`.
+const QUAD: [f32; 8] = [-1., -1., 1., -1., -1., 1., 1., 1.];
+
+/// Draws a dish's trail map with WebGL: programs, buffers and textures are set up once in
+/// `new`, then each `draw` uploads the trail as a texture and draws one full-screen quad that
+/// looks every cell up in the palette's colormap. Palettes blending species are colored on
+/// the CPU and uploaded as they are.
+pub struct GlRenderer<G: Gl> {
+    gl: G,
+    program: G::Program,
+    quad: G::Buffer,
+    a_position: u32,
+    trail: G::Texture,
+    colormap: G::Texture,
+    u_direct: Option<G::Location>,
+    luminance: Vec<u8>,     // reused upload buffer, one byte per cell
+    frame: Frame,           // the same when blending species
+}
+impl<G: Gl> GlRenderer<G> {
+    pub fn new(gl: G) -> Result<GlRenderer<G>, String> {
+        let vert = compile_shader(&gl, GLC::VERTEX_SHADER, VERTEX_SHADER)?;
+        let frag = compile_shader(&gl, GLC::FRAGMENT_SHADER, FRAGMENT_SHADER)?;
+        let program = link_program(&gl, &vert, &frag)?;
+        gl.use_program(Some(&program));
+
+        let a_position = gl.get_attrib_location(&program, "a_position");
+        let a_position = u32::try_from(a_position).map_err(|_| String::from("no a_position attribute"))?;
+        let quad = gl.create_buffer().ok_or("failed to create buffer")?;
+        gl.bind_buffer(GLC::ARRAY_BUFFER, Some(&quad));
+        gl.buffer_data_f32(GLC::ARRAY_BUFFER, &QUAD, GLC::STATIC_DRAW);
+
+        // sampler units never change, so set them once
+        gl.uniform1i(gl.get_uniform_location(&program, "u_trail").as_ref(), 0);
+        gl.uniform1i(gl.get_uniform_location(&program, "u_colormap").as_ref(), 1);
+        let trail = create_texture(&gl, 0)?;
+        let colormap = create_texture(&gl, 1)?;
+        let u_direct = gl.get_uniform_location(&program, "u_direct");
+
+        let mut renderer = GlRenderer { gl, program, quad, a_position, trail, colormap, u_direct,
+                                        luminance: Vec::new(), frame: Frame::new() };
+        renderer.set_palette(Palette::default())?;
+        Ok(renderer)
+    }
+    pub fn set_palette(&mut self, palette: Palette) -> Result<(), String> {
+        palette.validate()?;
+        self.set_colormap(palette.lut().as_flattened())?;
+        self.frame.set_palette(palette);
+        Ok(())
+    }
+    /// Replace the colormap with 256 RGBA entries, from empty cells to full ones.
+    pub fn set_colormap(&self, rgba: &[u8]) -> Result<(), String> {
+        if rgba.len() != 256 * 4 {
+            return Err(format!("colormap needs 256 RGBA entries, got {} bytes", rgba.len()));
+        }
+        self.gl.active_texture(GLC::TEXTURE1);
+        self.gl.bind_texture(GLC::TEXTURE_2D, Some(&self.colormap));
+        self.gl.tex_image_2d(GLC::TEXTURE_2D, 0, GLC::RGBA as i32, 256, 1, GLC::RGBA, GLC::UNSIGNED_BYTE, rgba)
+    }
+    /// Upload `dish`'s total trail and draw it over a `width` x `height` viewport.
+    pub fn draw<T: TrailValue>(&mut self, dish: &Dish<T>, width: i32, height: i32) -> Result<(), String> {
+        let gl = &self.gl;
+        let direct = self.frame.palette().blends_species();
+        let (format, pixels) = if direct {
+            (GLC::RGBA, self.frame.draw(dish))
+        } else {
+            self.luminance.clear();
+            self.luminance.extend((0..dish.size_w * dish.size_h).map(|i| cell_value(dish, i)));
+            (GLC::LUMINANCE, &self.luminance[..])
+        };
+
+        gl.use_program(Some(&self.program));
+        gl.uniform1i(self.u_direct.as_ref(), direct as i32);
+        gl.active_texture(GLC::TEXTURE0);
+        gl.bind_texture(GLC::TEXTURE_2D, Some(&self.trail));
+        gl.pixel_storei(GLC::UNPACK_ALIGNMENT, 1); // rows of one-byte texels aren't 4-aligned
+        gl.tex_image_2d(GLC::TEXTURE_2D, 0, format as i32, dish.size_w as i32, dish.size_h as i32,
+                        format, GLC::UNSIGNED_BYTE, pixels)?;
+        gl.active_texture(GLC::TEXTURE1);
+        gl.bind_texture(GLC::TEXTURE_2D, Some(&self.colormap));
+
+        gl.bind_buffer(GLC::ARRAY_BUFFER, Some(&self.quad));
+        gl.vertex_attrib_pointer(self.a_position, 2, GLC::FLOAT, false, 0, 0);
+        gl.enable_vertex_attrib_array(self.a_position);
+        gl.viewport(0, 0, width, height);
+        gl.clear_color(0., 0., 0., 1.);
+        gl.clear(GLC::COLOR_BUFFER_BIT);
+        gl.draw_arrays(GLC::TRIANGLE_STRIP, 0, (QUAD.len() / 2) as i32);
+        Ok(())
+    }
+    pub fn context(&self) -> &G {
+        &self.gl
+    }
+}
+
+/// A texture on `unit` that samples exact cells and works at any size (WebGL 1 only allows
+/// `REPEAT` and mipmaps on power-of-two textures).
+fn create_texture<G: Gl>(gl: &G, unit: u32) -> Result<G::Texture, String> {
+    let tex = gl.create_texture().ok_or("failed to create texture")?;
+    gl.active_texture(GLC::TEXTURE0 + unit);
+    gl.bind_texture(GLC::TEXTURE_2D, Some(&tex));
+    gl.tex_parameteri(GLC::TEXTURE_2D, GLC::TEXTURE_WRAP_S,     GLC::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GLC::TEXTURE_2D, GLC::TEXTURE_WRAP_T,     GLC::CLAMP_TO_EDGE as i32);
+    gl.tex_parameteri(GLC::TEXTURE_2D, GLC::TEXTURE_MIN_FILTER, GLC::NEAREST as i32);
+    gl.tex_parameteri(GLC::TEXTURE_2D, GLC::TEXTURE_MAG_FILTER, GLC::NEAREST as i32);
+    Ok(tex)
+}
+
+// BEGIN YOINK https://rustwasm.github.io/wasm-bindgen/examples/webgl.html
+pub fn compile_shader<G: Gl>(
+    context: &G,
+    shader_type: u32,
+    source: &str,
+) -> Result<G::Shader, String> {
+    let shader = context
+        .create_shader(shader_type)
+        .ok_or_else(|| String::from("Unable to create shader object"))?;
+    context.shader_source(&shader, source);
+    context.compile_shader(&shader);
+
+    if context.shader_compiled(&shader) {
+        Ok(shader)
+    } else {
+        Err(context
+            .shader_info_log(&shader)
+            .unwrap_or_else(|| String::from("Unknown error creating shader")))
+    }
+}
+
+pub fn link_program<G: Gl>(
+    context: &G,
+    vert_shader: &G::Shader,
+    frag_shader: &G::Shader,
+) -> Result<G::Program, String> {
+    let program = context
+        .create_program()
+        .ok_or_else(|| String::from("Unable to create shader object"))?;
+
+    context.attach_shader(&program, vert_shader);
+    context.attach_shader(&program, frag_shader);
+    context.link_program(&program);
+
+    if context.program_linked(&program) {
+        Ok(program)
+    } else {
+        Err(context
+            .program_info_log(&program)
+            .unwrap_or_else(|| String::from("Unknown error creating program object")))
+    }
+}
+// END YOINK
@@ -0,0 +1,166 @@
+use serde::{ Deserialize, Serialize };
+
+use crate::trail::TrailValue;
+use crate::sim::World;
+use crate::vec2d::{ Boundary, Vec2d };
+
+/// A blur applied to the trail map each step. Implement this to diffuse with a custom kernel via
+/// `Dish::diffuse_with`; the built-in ones are the variants of `Kernel`.
+pub trait DiffusionKernel {
+    /// Largest |dy| or |dx| with a nonzero weight.
+    fn radius(&self) -> i32;
+    /// Unnormalized weight of the sample at offset (dy, dx); results are divided by the total.
+    fn weight(&self, dy: i32, dx: i32) -> f64;
+    /// Weights `w` for offsets -radius..=radius if `weight(dy, dx)` is `w[dy] * w[dx]`, letting
+    /// the blur run as a row pass then a column pass: O(radius) per cell rather than O(radius²).
+    fn separable(&self) -> Option<Vec<f64>> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Kernel {
+    /// Uniform average over a (2r+1)x(2r+1) square.
+    Box { radius: i32 },
+    /// Isotropic Gaussian, truncated at 3 sigma. It's the product of two 1D ones, so it's blurred
+    /// as a row pass then a column pass; with walls, which cut taps off individually, it falls
+    /// back to the full 2D stencil.
+    Gaussian { sigma: f64 },
+    /// Elliptical Gaussian stretched along `angle` (radians), smearing trails in that direction.
+    Anisotropic { sigma_along: f64, sigma_across: f64, angle: f64 },
+}
+impl Kernel {
+    pub fn validate(&self) -> Result<(), String> {
+        let ok = match *self {
+            Kernel::Box { radius } => radius >= 0,
+            Kernel::Gaussian { sigma } => sigma.is_finite() && sigma > 0.,
+            Kernel::Anisotropic { sigma_along, sigma_across, angle } =>
+                sigma_along.is_finite() && sigma_along > 0. && sigma_across.is_finite() && sigma_across > 0.
+                && angle.is_finite(),
+        };
+        if ok { Ok(()) } else { Err(format!("invalid diffusion kernel {:?}", self)) }
+    }
+}
+impl DiffusionKernel for Kernel {
+    fn radius(&self) -> i32 {
+        match *self {
+            Kernel::Box { radius } => radius,
+            Kernel::Gaussian { sigma } => (3. * sigma).ceil() as i32,
+            Kernel::Anisotropic { sigma_along, sigma_across, .. } => (3. * sigma_along.max(sigma_across)).ceil() as i32,
+        }
+    }
+    fn weight(&self, dy: i32, dx: i32) -> f64 {
+        let (dy, dx) = (dy as f64, dx as f64);
+        match *self {
+            Kernel::Box { .. } => 1.,
+            Kernel::Gaussian { sigma } => gaussian(dy, sigma) * gaussian(dx, sigma),
+            Kernel::Anisotropic { sigma_along, sigma_across, angle } => {
+                let along  =  dx * angle.cos() + dy * angle.sin();
+                let across = -dx * angle.sin() + dy * angle.cos();
+                gaussian(along, sigma_along) * gaussian(across, sigma_across)
+            }
+        }
+    }
+    fn separable(&self) -> Option<Vec<f64>> {
+        match *self {
+            Kernel::Gaussian { sigma } => {
+                let r = self.radius();
+                Some((-r..=r).map(|d| gaussian(d as f64, sigma)).collect())
+            }
+            _ => None,
+        }
+    }
+}
+
+fn gaussian(d: f64, sigma: f64) -> f64 {
+    (-d * d / (2. * sigma * sigma)).exp()
+}
+
+/// A kernel's nonzero taps, precomputed once per diffusion pass.
+pub(crate) struct Stencil {
+    pub radius: i32,
+    pub taps: Vec<(i32, i32, f64)>,
+    pub norm: f64,
+    line: Option<(Vec<f64>, f64)>,  // `DiffusionKernel::separable` weights and their total squared
+}
+impl Stencil {
+    pub fn new<K: DiffusionKernel + ?Sized>(kernel: &K) -> Stencil {
+        let radius = kernel.radius();
+        let taps: Vec<_> = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dy, dx)))
+            .map(|(dy, dx)| (dy, dx, kernel.weight(dy, dx)))
+            .filter(|&(_, _, w)| w != 0.)
+            .collect();
+        let norm = taps.iter().map(|t| t.2).sum();
+        let line = kernel.separable().map(|line| {
+            let total: f64 = line.iter().sum();
+            (line, total * total)
+        });
+        Stencil { radius, taps, norm, line }
+    }
+    /// Whether to blur with `apply_separable` rather than `apply` in `world`.
+    pub fn separable(&self, world: &World) -> bool {
+        self.line.is_some() && world.walls.is_none()
+    }
+    /// `apply` for the `ch`th of `channels` as a column pass over row passes, which `rows`
+    /// computes on first use and keeps for neighbouring cells. Only when `separable`.
+    pub fn apply_separable<T: TrailValue>(&self, channels: &[Vec2d<T>], rows: &mut RowPass, ch: usize,
+                                          (cy, cx): (i32, i32), mix: f64, boundary: Boundary) -> T {
+        let (line, norm) = self.line.as_ref().expect("separable stencil");
+        let r = self.radius;
+        let mut sum = 0.;
+        for (dy, &w) in (-r..=r).zip(line) {
+            let Some(c) = rows.done.resolve((cy + dy, cx), boundary) else { continue };
+            if !rows.done[c] {
+                for (channel, sums) in channels.iter().zip(&mut rows.sums) {
+                    sums[c] = (-r..=r).zip(line)
+                        .map(|(dx, &w)| channel.get((c.0, c.1 + dx), boundary).map_or(0., |v| w * v.to_f64()))
+                        .sum();
+                }
+                rows.done[c] = true;
+            }
+            sum += w * rows.sums[ch][c];
+        }
+        T::from_f64(mix * (sum / norm) + (1. - mix) * channels[ch][(cy, cx)].to_f64())
+    }
+    /// Blurred value of cell (cy, cx), mixed with the original: `mix` 1 is fully blurred.
+    /// Taps behind a wall are left out and the rest reweighted, so trail doesn't leak through.
+    pub fn apply<T: TrailValue>(&self, channel: &Vec2d<T>, cy: i32, cx: i32, mix: f64, world: &World) -> T {
+        let boundary = world.boundary;
+        let (sum, norm) = if world.walls.is_none() {
+            let sum: f64 = self.taps.iter()
+                .map(|&(dy, dx, w)| channel.get((cy + dy, cx + dx), boundary).map_or(0., |v| w * v.to_f64()))
+                .sum();
+            (sum, self.norm)
+        } else if world.is_wall((cy, cx)) {
+            return T::default();
+        } else {
+            let (mut sum, mut norm) = (0., 0.);
+            for &(dy, dx, w) in &self.taps {
+                if world.blocked((cy as f64, cx as f64), ((cy + dy) as f64, (cx + dx) as f64)) { continue }
+                sum += channel.get((cy + dy, cx + dx), boundary).map_or(0., |v| w * v.to_f64());
+                norm += w;
+            }
+            (sum, norm)
+        };
+        T::from_f64(mix * (sum / norm) + (1. - mix) * channel[(cy, cx)].to_f64())
+    }
+}
+
+/// Scratch for `Stencil::apply_separable`: each channel's row pass, filled in as cells need it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RowPass {
+    sums: Vec<Vec2d<f64>>,
+    done: Vec2d<bool>,
+}
+impl RowPass {
+    /// Forget the last pass, reallocating if the world or channel count changed.
+    pub fn reset(&mut self, channels: usize, size_w: usize, size_h: usize) {
+        if self.sums.len() != channels || (self.done.size_w, self.done.size_h) != (size_w, size_h) {
+            self.sums = vec![Vec2d::new(size_w, size_h, 0.); channels];
+            self.done = Vec2d::new(size_w, size_h, false);
+        } else {
+            self.done.for_each(|d| *d = false);
+        }
+    }
+}
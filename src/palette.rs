@@ -0,0 +1,171 @@
+use crate::sim::Dish;
+use crate::trail::TrailValue;
+
+/// Maps trail intensity (0 empty, 1 full) to a color.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Colormap {
+    Gray,
+    Viridis,
+    Magma,
+    Inferno,
+    /// Linear blend between `(position, rgb)` stops, positions rising from 0 to 1.
+    Gradient(Vec<(f64, [u8; 3])>),
+}
+impl Colormap {
+    /// A colormap by name (gray, viridis, magma, inferno), or a comma-separated list of
+    /// `#rrggbb` colors spread evenly from empty to full.
+    pub fn parse(spec: &str) -> Result<Colormap, String> {
+        Ok(match spec {
+            "gray"    => Colormap::Gray,
+            "viridis" => Colormap::Viridis,
+            "magma"   => Colormap::Magma,
+            "inferno" => Colormap::Inferno,
+            _ => {
+                let colors = spec.split(',').map(parse_color).collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("{} (or a colormap: gray, viridis, magma, inferno)", e))?;
+                let last = colors.len().saturating_sub(1).max(1) as f64;
+                let colormap = Colormap::Gradient(colors.into_iter().enumerate().map(|(i, c)| (i as f64 / last, c)).collect());
+                colormap.validate()?;
+                colormap
+            }
+        })
+    }
+    pub fn validate(&self) -> Result<(), String> {
+        if let Colormap::Gradient(stops) = self {
+            let rising = stops.windows(2).all(|w| w[0].0 <= w[1].0);
+            if stops.is_empty() || !rising || stops.iter().any(|s| !(0. ..=1.).contains(&s.0)) {
+                return Err(format!("gradient stops must be in [0, 1] and rising, got {:?}", stops));
+            }
+        }
+        Ok(())
+    }
+    /// Color at `t` in [0, 1].
+    pub fn color(&self, t: f64) -> [u8; 3] {
+        match self {
+            Colormap::Gray => {
+                let v = (t * 255.).round() as u8;
+                [v, v, v]
+            }
+            Colormap::Viridis => even_stops(&VIRIDIS, t),
+            Colormap::Magma   => even_stops(&MAGMA, t),
+            Colormap::Inferno => even_stops(&INFERNO, t),
+            Colormap::Gradient(stops) => {
+                let next = stops.iter().position(|s| s.0 > t).unwrap_or(stops.len());
+                if next == 0 { return stops[0].1 }
+                if next == stops.len() { return stops[next - 1].1 }
+                let ((t0, c0), (t1, c1)) = (stops[next - 1], stops[next]);
+                lerp(c0, c1, (t - t0) / (t1 - t0))
+            }
+        }
+    }
+}
+
+/// How trail values are stretched before coloring, to bring out faint trails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Intensity {
+    Linear,
+    /// `t^gamma`; below 1 brightens faint trail.
+    Gamma(f64),
+    /// `log(1 + 255 t) / log(256)`.
+    Log,
+}
+impl Intensity {
+    pub fn apply(self, t: f64) -> f64 {
+        match self {
+            Intensity::Linear => t,
+            Intensity::Gamma(gamma) => t.powf(gamma),
+            Intensity::Log => (255. * t).ln_1p() / 256f64.ln(),
+        }
+    }
+}
+
+/// How the canvas, WebGL and image outputs color the trail map: either `colormap` over every
+/// species' total trail, or, if `species_colors` is set, each species' own trail tinting its
+/// color (repeating if there are more species than colors), added together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub colormap: Colormap,
+    pub intensity: Intensity,
+    pub species_colors: Vec<[u8; 3]>,
+}
+impl Default for Palette {
+    fn default() -> Self {
+        Palette { colormap: Colormap::Gray, intensity: Intensity::Linear, species_colors: Vec::new() }
+    }
+}
+impl Palette {
+    pub fn validate(&self) -> Result<(), String> {
+        self.colormap.validate()?;
+        if let Intensity::Gamma(gamma) = self.intensity {
+            if !(gamma.is_finite() && gamma > 0.) {
+                return Err(format!("gamma must be positive, got {}", gamma));
+            }
+        }
+        Ok(())
+    }
+    pub fn blends_species(&self) -> bool {
+        !self.species_colors.is_empty()
+    }
+    /// RGBA color of every displayed trail value 0..=255, through `intensity` and `colormap`.
+    pub fn lut(&self) -> Vec<[u8; 4]> {
+        (0..=255).map(|v| {
+            let [r, g, b] = self.colormap.color(self.intensity.apply(v as f64 / 255.));
+            [r, g, b, 255]
+        }).collect()
+    }
+    /// RGBA color of the `i`th cell in row-major order; `lut` is this palette's `lut()`.
+    pub fn paint<T: TrailValue>(&self, dish: &Dish<T>, lut: &[[u8; 4]], i: usize) -> [u8; 4] {
+        if !self.blends_species() {
+            return lut[cell_value(dish, i) as usize];
+        }
+        let mut rgb = [0.; 3];
+        for (channel, color) in dish.data.iter().zip(self.species_colors.iter().cycle()) {
+            let t = self.intensity.apply(channel.as_slice()[i].to_display() as f64 / 255.);
+            for (sum, &c) in rgb.iter_mut().zip(color) {
+                *sum += t * c as f64;
+            }
+        }
+        [rgb[0].round().min(255.) as u8, rgb[1].round().min(255.) as u8, rgb[2].round().min(255.) as u8, 255]
+    }
+}
+
+/// Total trail of the `i`th cell in row-major order, as drawn; see `Dish::total_trail`.
+pub(crate) fn cell_value<T: TrailValue>(dish: &Dish<T>, i: usize) -> u8 {
+    let total: f64 = dish.data.iter().map(|channel| channel.as_slice()[i].to_f64()).sum();
+    T::from_f64(total).to_display()
+}
+
+/// `#rrggbb` or `rrggbb`.
+pub fn parse_color(spec: &str) -> Result<[u8; 3], String> {
+    let hex = spec.trim().trim_start_matches('#');
+    let channel = |i: usize| hex.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok());
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(format!("bad color `{}`, expected #rrggbb", spec)),
+    }
+}
+
+fn lerp(a: [u8; 3], b: [u8; 3], f: f64) -> [u8; 3] {
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+    [mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])]
+}
+
+fn even_stops(stops: &[[u8; 3]], t: f64) -> [u8; 3] {
+    let p = t.clamp(0., 1.) * (stops.len() - 1) as f64;
+    let i = (p.floor() as usize).min(stops.len() - 2);
+    lerp(stops[i], stops[i + 1], p - i as f64)
+}
+
+// matplotlib's colormaps sampled at 11 evenly spaced points
+const VIRIDIS: [[u8; 3]; 11] = [
+    [0x44, 0x01, 0x54], [0x48, 0x24, 0x75], [0x41, 0x44, 0x87], [0x35, 0x5f, 0x8d], [0x2a, 0x78, 0x8e], [0x21, 0x91, 0x8c],
+    [0x22, 0xa8, 0x84], [0x44, 0xbf, 0x70], [0x7a, 0xd1, 0x51], [0xbd, 0xdf, 0x26], [0xfd, 0xe7, 0x25],
+];
+const MAGMA: [[u8; 3]; 11] = [
+    [0x00, 0x00, 0x04], [0x14, 0x0e, 0x36], [0x3b, 0x0f, 0x70], [0x64, 0x1a, 0x80], [0x8c, 0x29, 0x81], [0xb7, 0x37, 0x79],
+    [0xde, 0x49, 0x68], [0xf7, 0x70, 0x5c], [0xfe, 0x9f, 0x6d], [0xfe, 0xcf, 0x92], [0xfc, 0xfd, 0xbf],
+];
+const INFERNO: [[u8; 3]; 11] = [
+    [0x00, 0x00, 0x04], [0x16, 0x0b, 0x39], [0x42, 0x0a, 0x68], [0x6a, 0x17, 0x6e], [0x93, 0x26, 0x67], [0xbc, 0x37, 0x54],
+    [0xdd, 0x51, 0x3a], [0xf3, 0x78, 0x19], [0xfc, 0xa5, 0x0a], [0xf6, 0xd7, 0x46], [0xfc, 0xff, 0xa4],
+];
@@ -0,0 +1,396 @@
+use std::f64::consts::PI;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::kernel::{ DiffusionKernel, Kernel };
+use crate::spawn::SpawnPattern;
+use crate::steering::SteeringMode;
+use crate::vec2d::Boundary;
+
+/// Per-species agent behaviour. Each species deposits into its own trail channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeciesParams {
+    pub num_agents: usize,
+
+    pub sensors: SensorArray,
+    pub sensor_shape: SensorShape,
+    pub sensor_radius: f64,
+    pub sensor_angle: f64,      // radians, offset of the outermost fanned sensors from heading
+    pub sensor_distance: f64,
+    pub turn_angle: f64,        // radians
+    pub velocity: f64,
+    pub deposit_amount: f64,    // trail added per agent per step
+    pub steering: SteeringMode,
+    pub heading_jitter: f64,    // radians, uniform random rotation in [-j, j] added every step
+
+    pub weights: Vec<f64>,      // sensed value = sum over channels c of weights[c] * trail[c]
+    pub nutrient_weight: f64,   // ... plus nutrient_weight * the dish's nutrient field, if any
+    pub repellent_weight: f64,  // ... minus repellent_weight * its repellent field, if any
+}
+impl Default for SpeciesParams {
+    fn default() -> Self {
+        SpeciesParams {
+            num_agents: 800,
+
+            sensors: SensorArray::Fan { count: 3 },
+            sensor_shape: SensorShape::Square,
+            sensor_radius: 2.,
+            sensor_angle: PI/3.,
+            sensor_distance: 8.,
+            turn_angle: PI/12.,
+            velocity: 2.,
+            deposit_amount: 255.,
+            steering: SteeringMode::Weighted,
+            heading_jitter: 0.,
+
+            weights: vec![1.],
+            nutrient_weight: 1.,
+            repellent_weight: 1.,
+        }
+    }
+}
+
+/// Where an agent's sensors point, as angles from its heading. Positive angles are on the side
+/// a positive turn heads toward (`lef` in the classic three-sensor layout).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SensorArray {
+    /// `count` sensors spread evenly from `+sensor_angle` to `-sensor_angle`; 3 is the classic
+    /// left/forward/right layout, 1 is a single forward sensor.
+    Fan { count: usize },
+    /// Explicit angles in radians, e.g. a fan plus a rear sensor at pi.
+    Custom(Vec<f64>),
+}
+impl SensorArray {
+    pub fn count(&self) -> usize {
+        match self {
+            SensorArray::Fan { count } => *count,
+            SensorArray::Custom(angles) => angles.len(),
+        }
+    }
+    /// Angle of sensor `i` from the heading.
+    pub fn offset(&self, i: usize, sensor_angle: f64) -> f64 {
+        match self {
+            SensorArray::Fan { count: 1 } => 0.,
+            SensorArray::Fan { count } => sensor_angle - i as f64 * (sensor_angle * 2. / (count - 1) as f64),
+            SensorArray::Custom(angles) => angles[i],
+        }
+    }
+}
+
+/// Which cells around a sensor point are summed, and how they're weighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SensorShape {
+    Point,          // just the cell the sensor is in
+    Square,         // every cell within `sensor_radius` along both axes
+    Disk,           // every cell within `sensor_radius` of the center
+    GaussianDisk,   // the disk, weighted by a Gaussian with sigma = radius / 2
+}
+impl SensorShape {
+    /// Largest |dy| or |dx| that can have a nonzero weight.
+    pub fn reach(self, radius: f64) -> i32 {
+        match self {
+            SensorShape::Point => 0,
+            _ => radius.floor() as i32,
+        }
+    }
+    /// Weight of the cell at offset (dy, dx) from the sensor's cell; 0 outside the footprint.
+    pub fn weight(self, dy: i32, dx: i32, radius: f64) -> f64 {
+        let (r, d2) = (self.reach(radius), (dy*dy + dx*dx) as f64);
+        if dy.abs() > r || dx.abs() > r {
+            return 0.;
+        }
+        match self {
+            SensorShape::Point | SensorShape::Square => 1.,
+            SensorShape::Disk => if d2 <= radius * radius { 1. } else { 0. },
+            SensorShape::GaussianDisk => if d2 <= radius * radius { (-2. * d2 / (radius * radius)).exp() } else { 0. },
+        }
+    }
+}
+
+/// How an agent's deposit is spread over the trail map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepositMode {
+    Nearest,    // all into the cell the agent is in
+    Bilinear,   // split across the four cells around the agent's sub-pixel position
+}
+
+/// How much trail each cell loses per step.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Decay {
+    Multiplicative { factor: f64 },             // v * factor
+    Linear { amount: f64 },                     // v - amount, floored at 0
+    HalfLife { steps: f64 },                    // halves every `steps` steps
+    Threshold { factor: f64, cutoff: f64 },     // v * factor, then 0 if below cutoff
+}
+impl Decay {
+    pub fn apply(self, v: f64) -> f64 {
+        match self {
+            Decay::Multiplicative { factor } => v * factor,
+            Decay::Linear { amount } => (v - amount).max(0.),
+            Decay::HalfLife { steps } => v * 0.5f64.powf(1. / steps),
+            Decay::Threshold { factor, cutoff } => {
+                let v = v * factor;
+                if v < cutoff { 0. } else { v }
+            }
+        }
+    }
+    fn validate(self) -> Result<(), String> {
+        match self {
+            Decay::Multiplicative { factor } => check((0. ..=1.).contains(&factor), "decay must be in [0, 1]"),
+            Decay::Linear { amount } => check(amount.is_finite() && amount >= 0., "decay amount must be finite and >= 0"),
+            Decay::HalfLife { steps } => check(steps.is_finite() && steps > 0., "decay half-life must be finite and > 0"),
+            Decay::Threshold { factor, cutoff } => {
+                check((0. ..=1.).contains(&factor), "decay must be in [0, 1]")?;
+                check(cutoff.is_finite() && cutoff >= 0., "decay cutoff must be finite and >= 0")
+            }
+        }
+    }
+}
+
+/// Longest side a world can have, in cells.
+pub const MAX_SIZE: usize = 8192;
+/// Most agents a dish can spawn, over all species.
+pub const MAX_AGENTS: usize = 4_000_000;
+/// Most species a dish can have; each senses every species' trail, so the cost is quadratic.
+pub const MAX_SPECIES: usize = 64;
+
+/// Tunable simulation parameters. `Default` matches the original hard-coded constants.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimParams {
+    pub size_w: usize,
+    pub size_h: usize,
+    pub boundary: Boundary,
+
+    pub kernel: Kernel,
+    pub diffuse_mix: f64,       // 1 replaces each cell with its blur, 0 disables diffusion
+    pub decay: Decay,
+    pub deposit_mode: DepositMode,
+    pub spawn: SpawnPattern,    // where `Dish::new` and `Dish::reset` place agents
+
+    pub species: Vec<SpeciesParams>,
+}
+impl Default for SimParams {
+    fn default() -> Self {
+        SimParams {
+            size_w: 512,
+            size_h: 256,
+            boundary: Boundary::Periodic,
+
+            kernel: Kernel::Box { radius: 1 },
+            diffuse_mix: 1.,
+            decay: Decay::Multiplicative { factor: 0.97 },
+            deposit_mode: DepositMode::Nearest,
+            spawn: SpawnPattern::default(),
+
+            species: vec![SpeciesParams::default()],
+        }
+    }
+}
+impl SimParams {
+    /// Default params with `n` species, each attracted to its own trail and repelled by the others'.
+    pub fn with_species(n: usize) -> SimParams {
+        SimParams { species: multi_species(&SpeciesParams::default(), n), ..SimParams::default() }
+    }
+    /// Set a single parameter by field name, e.g. from a CLI flag or the JS API.
+    /// Species fields without a prefix apply to every species; `species.<i>.<field>` targets
+    /// one, and `species.<i>.weight.<j>` sets how species `i` responds to channel `j`.
+    /// `species` itself resets to that many species modeled on the first. `diffuse_radius`,
+    /// `gaussian_sigma` and the `anisotropic_*` names switch the kernel to that shape; likewise
+    /// `decay`, `decay_linear`, `decay_half_life` and `decay_cutoff` pick the decay law.
+    /// Doesn't validate, except to refuse a species count it can't allocate; call `validate`
+    /// once all fields are set.
+    pub fn set(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "size_w"          => self.size_w = value as usize,
+            "size_h"          => self.size_h = value as usize,
+            "diffuse_radius"  => self.kernel = Kernel::Box { radius: value as i32 },
+            "gaussian_sigma"  => self.kernel = Kernel::Gaussian { sigma: value },
+            "anisotropic_along" | "anisotropic_across" | "anisotropic_angle" => {
+                let (mut sigma_along, mut sigma_across, mut angle) = match self.kernel {
+                    Kernel::Anisotropic { sigma_along, sigma_across, angle } => (sigma_along, sigma_across, angle),
+                    _ => (2., 0.5, 0.),
+                };
+                match name {
+                    "anisotropic_along"  => sigma_along = value,
+                    "anisotropic_across" => sigma_across = value,
+                    _                    => angle = value,
+                }
+                self.kernel = Kernel::Anisotropic { sigma_along, sigma_across, angle };
+            }
+            "diffuse_mix"     => self.diffuse_mix = value,
+            "decay"           => self.decay = match self.decay {
+                Decay::Threshold { cutoff, .. } => Decay::Threshold { factor: value, cutoff },
+                _ => Decay::Multiplicative { factor: value },
+            },
+            "decay_linear"    => self.decay = Decay::Linear { amount: value },
+            "decay_half_life" => self.decay = Decay::HalfLife { steps: value },
+            "decay_cutoff"    => self.decay = match self.decay {
+                Decay::Multiplicative { factor } | Decay::Threshold { factor, .. } => Decay::Threshold { factor, cutoff: value },
+                _ => Decay::Threshold { factor: 1., cutoff: value },
+            },
+            "species"         => {
+                // checked here as well as in `validate`, since the weights are n x n
+                if !(1. ..=MAX_SPECIES as f64).contains(&value) {
+                    return Err(format!("species must be 1 to {}, got {}", MAX_SPECIES, value));
+                }
+                let base = self.species.first().cloned().unwrap_or_default();
+                self.species = multi_species(&base, value as usize);
+            }
+            _ => {
+                let (species, field) = self.species_for(name)?;
+                for sp in species {
+                    sp.set(field, value).map_err(|_| format!("unknown parameter `{}`", name))?;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Like `set`, but also accepts the names of enum-valued modes, e.g. `deposit_mode=bilinear`,
+    /// `boundary=reflective` (periodic, reflective, absorbing, clamped), `sensor_shape=disk`
+    /// (point, square, disk, gaussian) or `steering=jones` (weighted, jones, gradient).
+    /// `sensors` takes a comma-separated list of angles, e.g. `sensors=1,0,-1,3.14`.
+    /// `spawn` picks a `SpawnPattern` (uniform, disk, ring, point, gaussian), tuned with
+    /// `spawn_x`, `spawn_y`, `spawn_radius` and `spawn_facing` (inward, outward, tangent, random).
+    pub fn set_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match (name, value) {
+            ("deposit_mode", "nearest")  => self.deposit_mode = DepositMode::Nearest,
+            ("deposit_mode", "bilinear") => self.deposit_mode = DepositMode::Bilinear,
+            ("boundary", "periodic")     => self.boundary = Boundary::Periodic,
+            ("boundary", "reflective")   => self.boundary = Boundary::Reflective,
+            ("boundary", "absorbing")    => self.boundary = Boundary::Absorbing,
+            ("boundary", "clamped")      => self.boundary = Boundary::Clamped,
+            ("spawn", _)                 => self.spawn.set_kind(value)?,
+            _ if name.starts_with("spawn_") => self.spawn.set(name, value)?,
+            _ if name.rsplit('.').next() == Some("steering") => {
+                let steering = match value {
+                    "weighted" => SteeringMode::Weighted,
+                    "jones"    => SteeringMode::Jones,
+                    "gradient" => SteeringMode::Gradient,
+                    _ => return Err(format!("bad value `{}` for `{}`", value, name)),
+                };
+                for sp in self.species_for(name)?.0 {
+                    sp.steering = steering;
+                }
+            }
+            _ if name.rsplit('.').next() == Some("sensors") => {
+                let angles = value.split(',')
+                    .map(|a| a.trim().parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| format!("bad value `{}` for `{}`", value, name))?;
+                for sp in self.species_for(name)?.0 {
+                    sp.sensors = SensorArray::Custom(angles.clone());
+                }
+            }
+            _ if name.rsplit('.').next() == Some("sensor_shape") => {
+                let shape = match value {
+                    "point"    => SensorShape::Point,
+                    "square"   => SensorShape::Square,
+                    "disk"     => SensorShape::Disk,
+                    "gaussian" => SensorShape::GaussianDisk,
+                    _ => return Err(format!("bad value `{}` for `{}`", value, name)),
+                };
+                for sp in self.species_for(name)?.0 {
+                    sp.sensor_shape = shape;
+                }
+            }
+            _ => {
+                let value = value.parse().map_err(|_| format!("bad value `{}` for `{}`", value, name))?;
+                self.set(name, value)?;
+            }
+        }
+        Ok(())
+    }
+    /// The species a name like `species.<i>.<field>` targets, or all of them if it has no
+    /// prefix, and the field name with the prefix stripped.
+    fn species_for<'a>(&mut self, name: &'a str) -> Result<(&mut [SpeciesParams], &'a str), String> {
+        match name.strip_prefix("species.") {
+            Some(rest) => {
+                let (idx, field) = rest.split_once('.').ok_or_else(|| format!("unknown parameter `{}`", name))?;
+                let i = idx.parse::<usize>().ok()
+                    .filter(|&i| i < self.species.len())
+                    .ok_or_else(|| format!("no species `{}`", idx))?;
+                Ok((&mut self.species[i..=i], field))
+            }
+            None => Ok((&mut self.species[..], name)),
+        }
+    }
+    pub fn validate(&self) -> Result<(), String> {
+        check(self.size_w > 0 && self.size_h > 0, "world size must be nonzero")?;
+        check(self.size_w <= MAX_SIZE && self.size_h <= MAX_SIZE, &format!("world size must be at most {} per side", MAX_SIZE))?;
+        self.kernel.validate()?;
+        check((self.kernel.radius() as usize) * 2 < self.size_w.min(self.size_h),
+              "diffusion kernel must be smaller than half the world")?;
+        check((0. ..=1.).contains(&self.diffuse_mix), "diffuse_mix must be in [0, 1]")?;
+        self.decay.validate()?;
+        self.spawn.validate(self.size_w, self.size_h)?;
+        check(!self.species.is_empty(), "need at least one species")?;
+        check(self.species.len() <= MAX_SPECIES, &format!("at most {} species", MAX_SPECIES))?;
+        check(self.species.iter().map(|sp| sp.num_agents).try_fold(0usize, usize::checked_add).is_some_and(|n| n <= MAX_AGENTS),
+              &format!("at most {} agents over all species", MAX_AGENTS))?;
+        for (i, sp) in self.species.iter().enumerate() {
+            sp.validate(self.species.len(), self.size_w.min(self.size_h)).map_err(|e| format!("species {}: {}", i, e))?;
+        }
+        Ok(())
+    }
+}
+impl SpeciesParams {
+    /// Angle of sensor `i` from the heading, see `SensorArray`.
+    pub fn sensor_offset(&self, i: usize) -> f64 {
+        self.sensors.offset(i, self.sensor_angle)
+    }
+    fn set(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "num_agents"      => self.num_agents = value as usize,
+            "sensor_radius"   => self.sensor_radius = value,
+            "sensor_angle"    => self.sensor_angle = value,
+            "sensor_distance" => self.sensor_distance = value,
+            "turn_angle"      => self.turn_angle = value,
+            "velocity"        => self.velocity = value,
+            "deposit_amount"  => self.deposit_amount = value,
+            "heading_jitter"  => self.heading_jitter = value,
+            "sensor_count"    => self.sensors = SensorArray::Fan { count: value as usize },
+            "nutrient_weight" => self.nutrient_weight = value,
+            "repellent_weight" => self.repellent_weight = value,
+            _ => {
+                let w = name.strip_prefix("weight.")
+                    .and_then(|j| j.parse::<usize>().ok())
+                    .and_then(|j| self.weights.get_mut(j))
+                    .ok_or_else(|| format!("unknown parameter `{}`", name))?;
+                *w = value;
+            }
+        }
+        Ok(())
+    }
+    fn validate(&self, num_channels: usize, shorter_side: usize) -> Result<(), String> {
+        check(self.sensor_radius.is_finite() && self.sensor_radius >= 0., "sensor_radius must be finite and >= 0")?;
+        check((self.sensor_shape.reach(self.sensor_radius) as usize) * 2 < shorter_side,
+              "sensor footprint must be smaller than half the world")?;
+        check((0. ..=PI).contains(&self.sensor_angle), "sensor_angle must be in [0, pi]")?;
+        check(self.sensors.count() > 0, "need at least one sensor")?;
+        check(match &self.sensors {
+            SensorArray::Fan { .. } => true,
+            SensorArray::Custom(angles) => angles.iter().all(|a| a.is_finite()),
+        }, "sensor angles must be finite")?;
+        check(self.sensor_distance.is_finite() && self.sensor_distance >= 0., "sensor_distance must be finite and >= 0")?;
+        check((0. ..=PI).contains(&self.turn_angle), "turn_angle must be in [0, pi]")?;
+        check(self.velocity.is_finite() && self.velocity >= 0., "velocity must be finite and >= 0")?;
+        check(self.deposit_amount.is_finite() && self.deposit_amount >= 0., "deposit_amount must be finite and >= 0")?;
+        check((0. ..=PI).contains(&self.heading_jitter), "heading_jitter must be in [0, pi]")?;
+        check(self.weights.len() == num_channels, "need one weight per species")?;
+        check(self.weights.iter().all(|w| w.is_finite()), "weights must be finite")?;
+        check(self.nutrient_weight.is_finite(), "nutrient_weight must be finite")?;
+        check(self.repellent_weight.is_finite(), "repellent_weight must be finite")?;
+        Ok(())
+    }
+}
+
+fn check(ok: bool, msg: &str) -> Result<(), String> {
+    if ok { Ok(()) } else { Err(String::from(msg)) }
+}
+
+fn multi_species(base: &SpeciesParams, n: usize) -> Vec<SpeciesParams> {
+    (0..n).map(|i| SpeciesParams {
+        weights: (0..n).map(|j| if i == j { 1. } else { -1. }).collect(),
+        ..base.clone()
+    }).collect()
+}
@@ -0,0 +1,49 @@
+use crate::palette::Palette;
+use crate::sim::Dish;
+use crate::trail::TrailValue;
+
+/// An RGBA8 picture of a dish's trail map, row by row from the top left, as the canvas'
+/// `ImageData` wants it. Keep one around and redraw it every frame to reuse its buffer.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub size_w: usize,
+    pub size_h: usize,
+    pixels: Vec<u8>,
+    palette: Palette,
+    lut: Vec<[u8; 4]>,      // `palette.lut()`
+}
+impl Default for Frame {
+    fn default() -> Self {
+        Frame::with_palette(Palette::default())
+    }
+}
+impl Frame {
+    /// A frame drawing in grayscale.
+    pub fn new() -> Frame {
+        Frame::default()
+    }
+    pub fn with_palette(palette: Palette) -> Frame {
+        Frame { size_w: 0, size_h: 0, pixels: Vec::new(), lut: palette.lut(), palette }
+    }
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.lut = palette.lut();
+        self.palette = palette;
+    }
+    /// Redraw from `dish` in this frame's palette. Follows the dish if it's been resized.
+    pub fn draw<T: TrailValue>(&mut self, dish: &Dish<T>) -> &[u8] {
+        let cells = dish.size_w * dish.size_h;
+        self.size_w = dish.size_w;
+        self.size_h = dish.size_h;
+        self.pixels.resize(cells * 4, 0);
+        for (i, pixel) in self.pixels.chunks_exact_mut(4).enumerate() {
+            pixel.copy_from_slice(&self.palette.paint(dish, &self.lut, i));
+        }
+        &self.pixels
+    }
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
@@ -0,0 +1,726 @@
+use rand::distributions::Uniform;
+use rand::prelude::{ Rng, RngCore, SeedableRng };
+use rand_pcg::Pcg32;
+use serde::{ Deserialize, Serialize };
+
+use std::f64::consts::PI;
+use std::mem::{ replace, swap, take };
+use std::collections::VecDeque; // NTFS OPTM: replace with queues = "1.1.0" CircularBuffer
+
+use crate::brush::{ Brush, Tool };
+use crate::kernel::{ DiffusionKernel, RowPass, Stencil };
+use crate::params::{ DepositMode, SensorShape, SimParams, SpeciesParams };
+use crate::spawn::SpawnPattern;
+use crate::steering::Steering;
+use crate::trail::TrailValue;
+use crate::vec2d::{ Boundary, Resize, Vec2d };
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Agent {
+    pub pos_x: f64,
+    pub pos_y: f64,
+    pub vel: f64,
+    pub heading: f64,   // radians
+    pub species: usize, // index into `SimParams::species` and the trail channels
+
+    pub prev: i32,      // last step's turn: -1 left, 1 right, 0 straight
+    pub sensed: Vec<f64>, // last step's reading of each sensor, see `SpeciesParams::sensors`
+}
+impl Agent {
+    /// `data` holds one trail channel per species; each is weighted by `params.weights` when sensed.
+    pub fn update<T: TrailValue>(&mut self, data: &[Vec2d<T>], params: &SpeciesParams, world: &World,
+                                 rng: &mut dyn RngCore) -> i32 {
+        self.update_with(data, params, world, &params.steering, rng)
+    }
+    /// Like `update`, turning with `steering` instead of the species' `SteeringMode`.
+    pub fn update_with<T: TrailValue, S: Steering + ?Sized>(&mut self, data: &[Vec2d<T>], params: &SpeciesParams,
+                                                            world: &World, steering: &S, rng: &mut dyn RngCore) -> i32 {
+        let SpeciesParams { sensor_distance: dist, sensor_radius: rad, sensor_shape: shape, .. } = *params;
+        let reach = shape.reach(rad);
+        let mut sensed = take(&mut self.sensed);
+        sensed.clear();
+        sensed.extend((0..params.sensors.count()).map(|i| {
+            let angle = self.heading + params.sensor_offset(i);
+            let (cy, cx) = (self.pos_y + dist * angle.sin(), self.pos_x + dist * angle.cos());
+            let center = (cy.round() as i32, cx.round() as i32);
+            let mut sum = 0.;
+            for (channel, &weight) in data.iter().zip(&params.weights) {
+                if weight == 0. { continue }
+                sum += weight * footprint(channel, center, shape, rad, reach, world.boundary);
+            }
+            if let (Some(nutrient), true) = (world.nutrient, params.nutrient_weight != 0.) {
+                sum += params.nutrient_weight * footprint(nutrient, center, shape, rad, reach, world.boundary);
+            }
+            if let (Some(repellent), true) = (world.repellent, params.repellent_weight != 0.) {
+                sum -= params.repellent_weight * footprint(repellent, center, shape, rad, reach, world.boundary);
+            }
+            sum
+        }));
+        self.sensed = sensed;
+
+        let turn = steering.steer(&self.sensed, self, params, rng);
+        self.heading += turn;
+        self.prev = if turn > 0. { -1 } else if turn < 0. { 1 } else { 0 };
+        if params.heading_jitter > 0. {
+            self.heading += rng.sample(Uniform::new_inclusive(-params.heading_jitter, params.heading_jitter));
+        }
+
+        // TODO: sensor checks
+        let (to_y, to_x) = (self.pos_y + self.vel * self.heading.sin(), self.pos_x + self.vel * self.heading.cos());
+        if world.walls.is_some() && world.blocked((self.pos_y, self.pos_x), (to_y, to_x)) {
+            // stay put and try another direction next step
+            self.heading = rng.sample(Uniform::new(0., PI*2.));
+            return self.prev;
+        }
+        let (pos_y, bounce_y) = world.boundary.confine(to_y, data[0].size_h);
+        let (pos_x, bounce_x) = world.boundary.confine(to_x, data[0].size_w);
+        if bounce_y { self.heading = -self.heading }
+        if bounce_x { self.heading = PI - self.heading }
+        self.pos_y = pos_y;
+        self.pos_x = pos_x;
+        self.prev
+    }
+    /// Cells and amounts this agent deposits; unused slots have zero amount.
+    pub fn deposit(&self, mode: DepositMode, amount: f64) -> [(i32, i32, f32); 4] {
+        match mode {
+            DepositMode::Nearest => {
+                let (y, x) = (self.pos_y.round() as i32, self.pos_x.round() as i32);
+                [(y, x, amount as f32), (y, x, 0.), (y, x, 0.), (y, x, 0.)]
+            }
+            DepositMode::Bilinear => {
+                let (y0, x0) = (self.pos_y.floor(), self.pos_x.floor());
+                let (fy, fx) = (self.pos_y - y0, self.pos_x - x0);
+                let (y0, x0) = (y0 as i32, x0 as i32);
+                [(y0,     x0,     (amount * (1.-fy) * (1.-fx)) as f32),
+                 (y0,     x0 + 1, (amount * (1.-fy) *     fx ) as f32),
+                 (y0 + 1, x0,     (amount *     fy  * (1.-fx)) as f32),
+                 (y0 + 1, x0 + 1, (amount *     fy  *     fx ) as f32)]
+            }
+        }
+    }
+}
+
+/// Weighted sum of `layer` over a sensor's footprint centered on `center`.
+fn footprint<V: TrailValue>(layer: &Vec2d<V>, center: (i32, i32), shape: SensorShape, rad: f64, reach: i32,
+                            boundary: Boundary) -> f64 {
+    let mut sum = 0.;
+    for dy in -reach..=reach {
+        for dx in -reach..=reach {
+            let w = shape.weight(dy, dx, rad);
+            if w > 0. {
+                sum += layer.get((center.0 + dy, center.1 + dx), boundary).map_or(0., |v| w * v.to_f64());
+            }
+        }
+    }
+    sum
+}
+
+/// What agents and diffusion run into besides the trail: the world's edges, its walls and
+/// the nutrient and repellent fields.
+#[derive(Debug, Clone, Copy)]
+pub struct World<'a> {
+    pub boundary: Boundary,
+    pub walls: Option<&'a Vec2d<bool>>,
+    pub nutrient: Option<&'a Vec2d<f32>>,
+    pub repellent: Option<&'a Vec2d<f32>>,
+}
+impl Default for World<'_> {
+    fn default() -> Self {
+        World { boundary: Boundary::Periodic, walls: None, nutrient: None, repellent: None }
+    }
+}
+impl World<'_> {
+    /// Cells past an absorbing edge aren't walls.
+    pub fn is_wall(&self, cell: (i32, i32)) -> bool {
+        self.walls.is_some_and(|walls| walls.get(cell, self.boundary).copied().unwrap_or(false))
+    }
+    /// Whether the straight line between two (y, x) points passes through a wall cell, or
+    /// squeezes diagonally between two. Starting inside a wall, the wall cells up to where
+    /// the line first leaves it don't count, so whatever walls are drawn over can get out.
+    pub fn blocked(&self, from: (f64, f64), to: (f64, f64)) -> bool {
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.);
+        let mut prev = (from.0.round() as i32, from.1.round() as i32);
+        let mut inside = self.is_wall(prev);
+        for k in 1..=steps as i32 {
+            let t = k as f64 / steps;
+            let cell = ((from.0 + (to.0 - from.0) * t).round() as i32, (from.1 + (to.1 - from.1) * t).round() as i32);
+            if cell == prev { continue }
+            if inside {
+                inside = self.is_wall(cell);
+            } else if self.is_wall(cell)
+               || cell.0 != prev.0 && cell.1 != prev.1 && self.is_wall((prev.0, cell.1)) && self.is_wall((cell.0, prev.1)) {
+                return true;
+            }
+            prev = cell;
+        }
+        false
+    }
+}
+
+/// A food source or other chemoattractant emitter: every step it adds `strength` to each
+/// species' trail and `repellent` to the repellent field on every cell within `radius` of it,
+/// then moves by its velocity, bouncing off walls and non-periodic edges.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub pos_x: f64,
+    pub pos_y: f64,
+    pub vel_x: f64,
+    pub vel_y: f64,
+    pub strength: f64,
+    pub repellent: f64,
+    pub radius: f64,
+}
+impl Default for Source {
+    fn default() -> Self {
+        Source { pos_x: 0., pos_y: 0., vel_x: 0., vel_y: 0., strength: 50., repellent: 0., radius: 2. }
+    }
+}
+impl Source {
+    pub fn validate(&self) -> Result<(), String> {
+        let finite = [self.pos_x, self.pos_y, self.vel_x, self.vel_y, self.strength, self.repellent, self.radius]
+            .iter().all(|v| v.is_finite());
+        if !(finite && self.strength >= 0. && self.repellent >= 0. && self.radius >= 0.) {
+            return Err(format!("invalid source {:?}", self));
+        }
+        Ok(())
+    }
+    /// Cells within `radius`, unresolved.
+    pub fn cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        disk(self.pos_y, self.pos_x, self.radius)
+    }
+    fn advance(&mut self, world: &World, size_w: usize, size_h: usize) {
+        let (to_y, to_x) = (self.pos_y + self.vel_y, self.pos_x + self.vel_x);
+        if world.blocked((self.pos_y, self.pos_x), (to_y, to_x)) {
+            self.vel_y = -self.vel_y;
+            self.vel_x = -self.vel_x;
+            return;
+        }
+        let (pos_y, bounce_y) = world.boundary.confine(to_y, size_h);
+        let (pos_x, bounce_x) = world.boundary.confine(to_x, size_w);
+        if bounce_y { self.vel_y = -self.vel_y }
+        if bounce_x { self.vel_x = -self.vel_x }
+        self.pos_y = pos_y;
+        self.pos_x = pos_x;
+    }
+}
+
+/// Cells within `radius` of the cell at (`y`, `x`), unresolved.
+fn disk(y: f64, x: f64, radius: f64) -> impl Iterator<Item = (i32, i32)> {
+    let (cy, cx) = (y.round() as i32, x.round() as i32);
+    let r = radius.floor() as i32;
+    (-r..=r).flat_map(move |dy| (-r..=r).map(move |dx| (dy, dx)))
+        .filter(move |&(dy, dx)| ((dy*dy + dx*dx) as f64) <= radius * radius)
+        .map(move |(dy, dx)| (cy + dy, cx + dx))
+}
+
+fn world<'a>(params: &SimParams, walls: &'a Option<Vec2d<bool>>, nutrient: &'a Option<Vec2d<f32>>,
+             repellent: &'a Option<Vec2d<f32>>) -> World<'a> {
+    World { boundary: params.boundary, walls: walls.as_ref(), nutrient: nutrient.as_ref(), repellent: repellent.as_ref() }
+}
+
+/// Each species' own `SteeringMode`.
+struct FromParams;
+impl Steering for FromParams {
+    fn steer(&self, sensed: &[f64], agent: &Agent, params: &SpeciesParams, rng: &mut dyn RngCore) -> f64 {
+        params.steering.steer(sensed, agent, params, rng)
+    }
+}
+
+/// The simulation state: agents plus the trail map they deposit into and sense.
+/// Has no browser dependency; see `web::WebDish` for the canvas front end.
+/// `T` is the trail value type, see `TrailValue`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Dish<T: TrailValue = u8> {
+    pub size_w: usize,
+    pub size_h: usize,
+
+    pub params: SimParams,
+    pub agents: Vec<Agent>,
+    pub data: Vec<Vec2d<T>>,            // one trail channel per species
+    pub walls: Option<Vec2d<bool>>,     // cells nothing can enter, see `set_walls`
+    pub sources: Vec<Source>,
+    pub nutrient: Option<Vec2d<f32>>,   // static field agents sense alongside the trail, see `set_nutrient`
+    pub repellent: Option<Vec2d<f32>>,  // diffused and decayed like the trail but subtracted when sensed, see `set_repellent`
+    repellent_set: Option<Vec2d<f32>>,  // as last given to `set_repellent`, what `reset` starts from
+    #[serde(skip)]
+    data_alt: Vec<Vec2d<T>>,            // scratch, rebuilt by `restore_scratch`
+    #[serde(skip)]
+    deposit_acc: Vec<Vec2d<f32>>,       // scratch, this step's deposits before they're folded into `data`
+    #[serde(skip)]
+    visited: Vec2d<bool>,               // inq, for SPFA style update
+    #[serde(skip)]
+    rows: RowPass,                      // scratch for separable kernels
+    active_cells: VecDeque<(i32, i32)>, // invariant: contains all active cells at beginning of render()
+
+    pub seed: u64,
+    rng: Pcg32,
+}
+impl Dish {
+    /// Runs are reproducible: the same params and seed give a bit-identical trail map.
+    pub fn new(params: SimParams, seed: u64) -> Result<Dish, String> {
+        Dish::with_trail(params, seed)
+    }
+}
+impl<T: TrailValue> Dish<T> {
+    /// Like `new`, for a trail stored as `T`, e.g. `Dish::<f32>::with_trail(params, seed)`.
+    pub fn with_trail(params: SimParams, seed: u64) -> Result<Dish<T>, String> {
+        params.validate()?;
+        let (size_w, size_h) = (params.size_w, params.size_h);
+
+        let mut rng = Pcg32::seed_from_u64(seed);
+
+        let agents = params.spawn.spawn(&params, &mut rng);
+        let active_cells = agents.iter().map(|agent| (agent.pos_y.round() as i32, agent.pos_x.round() as i32)).collect();
+
+        let channels = params.species.len();
+        Ok(Dish { size_w, size_h,
+               params,
+               agents,
+               data:     vec![Vec2d::new(size_w, size_h, T::default()); channels],
+               walls:    None,
+               sources:  Vec::new(),
+               nutrient: None,
+               repellent: None,
+               repellent_set: None,
+               data_alt: vec![Vec2d::new(size_w, size_h, T::default()); channels],
+               deposit_acc: vec![Vec2d::new(size_w, size_h, 0f32); channels],
+               visited:  Vec2d::new(size_w, size_h, false),
+               rows:     RowPass::default(),
+               active_cells,
+               seed,
+               rng,
+        })
+    }
+    /// Reallocate the per-step scratch buffers, which snapshots don't store.
+    pub(crate) fn restore_scratch(&mut self) {
+        self.data_alt = vec![Vec2d::new(self.size_w, self.size_h, T::default()); self.data.len()];
+        self.deposit_acc = vec![Vec2d::new(self.size_w, self.size_h, 0f32); self.data.len()];
+        self.visited  = Vec2d::new(self.size_w, self.size_h, false);
+    }
+    /// Block off cells, e.g. `Some(image.map(|&v| v < 128))` for dark walls in a maze image.
+    /// Trail under the walls is cleared. Agents inside them move freely until they walk out,
+    /// and from then on are kept out like any other.
+    pub fn set_walls(&mut self, walls: Option<Vec2d<bool>>) -> Result<(), String> {
+        if let Some(walls) = &walls {
+            if (walls.size_w, walls.size_h) != (self.size_w, self.size_h) {
+                return Err(format!("walls are {}x{} but the world is {}x{}", walls.size_w, walls.size_h, self.size_w, self.size_h));
+            }
+            for channel in &mut self.data {
+                for (v, &wall) in channel.as_mut_slice().iter_mut().zip(walls.as_slice()) {
+                    if wall { *v = T::default() }
+                }
+            }
+            for (v, &wall) in self.repellent.iter_mut().flat_map(|r| r.as_mut_slice()).zip(walls.as_slice()) {
+                if wall { *v = 0. }
+            }
+        }
+        self.walls = walls;
+        Ok(())
+    }
+    /// Start over as if freshly built with the same params and seed: agents respawn from
+    /// `params.spawn` and the trail is cleared. Walls, the nutrient field and sources are kept,
+    /// the latter where they've moved to, and the repellent goes back to what was last passed
+    /// to `set_repellent`, without what sources and the brush have added since or its spread.
+    pub fn reset(&mut self) {
+        self.rng = Pcg32::seed_from_u64(self.seed);
+        self.agents = self.params.spawn.spawn(&self.params, &mut self.rng);
+        self.active_cells = self.agents.iter().map(|agent| (agent.pos_y.round() as i32, agent.pos_x.round() as i32)).collect();
+        for channel in &mut self.data {
+            channel.for_each(|v| *v = T::default());
+        }
+        for acc in &mut self.deposit_acc {
+            acc.for_each(|v| *v = 0.);
+        }
+        self.repellent = self.repellent_set.clone();
+        self.wall_off_repellent();
+    }
+    /// `reset` with a different spawn pattern.
+    pub fn reset_with(&mut self, spawn: SpawnPattern) -> Result<(), String> {
+        spawn.validate(self.size_w, self.size_h)?;
+        self.params.spawn = spawn;
+        self.reset();
+        Ok(())
+    }
+    /// Give agents a static field to sense, weighted by each species' `nutrient_weight`,
+    /// e.g. `Some(image.map(|&v| v as f32))` to read food from an image.
+    pub fn set_nutrient(&mut self, nutrient: Option<Vec2d<f32>>) -> Result<(), String> {
+        if let Some(nutrient) = &nutrient {
+            if (nutrient.size_w, nutrient.size_h) != (self.size_w, self.size_h) {
+                return Err(format!("nutrient field is {}x{} but the world is {}x{}", nutrient.size_w, nutrient.size_h, self.size_w, self.size_h));
+            }
+        }
+        self.nutrient = nutrient;
+        Ok(())
+    }
+    /// Replace the repellent field, e.g. with hazard zones painted from an image. It spreads and
+    /// fades like the trail; sources with a `repellent` amount keep topping it up, and one is
+    /// created when they first need it.
+    pub fn set_repellent(&mut self, repellent: Option<Vec2d<f32>>) -> Result<(), String> {
+        if let Some(repellent) = &repellent {
+            if (repellent.size_w, repellent.size_h) != (self.size_w, self.size_h) {
+                return Err(format!("repellent field is {}x{} but the world is {}x{}", repellent.size_w, repellent.size_h, self.size_w, self.size_h));
+            }
+        }
+        self.repellent_set = repellent.clone();
+        self.repellent = repellent;
+        self.wall_off_repellent();
+        Ok(())
+    }
+    fn wall_off_repellent(&mut self) {
+        if let (Some(repellent), Some(walls)) = (&mut self.repellent, &self.walls) {
+            for (v, &wall) in repellent.as_mut_slice().iter_mut().zip(walls.as_slice()) {
+                if wall { *v = 0. }
+            }
+        }
+    }
+    /// Change the world size mid-run. The trail, nutrient and repellent fields are carried over
+    /// by `mode`, walls by nearest cell unless cropped, and agents and sources move along with
+    /// them, brought back in by the boundary if they end up outside.
+    pub fn resize(&mut self, size_w: usize, size_h: usize, mode: Resize) -> Result<(), String> {
+        let mut params = SimParams { size_w, size_h, ..self.params.clone() };
+        if let SpawnPattern::Image(density) = &mut params.spawn {
+            density.resize(size_w, size_h, mode);
+        }
+        params.validate()?;
+        let (old_w, old_h) = (self.size_w, self.size_h);
+        let remap = |y: f64, x: f64| (
+            params.boundary.confine(mode.remap(y, old_h, size_h), size_h).0,
+            params.boundary.confine(mode.remap(x, old_w, size_w), size_w).0,
+        );
+        for agent in &mut self.agents {
+            (agent.pos_y, agent.pos_x) = remap(agent.pos_y, agent.pos_x);
+        }
+        for source in &mut self.sources {
+            (source.pos_y, source.pos_x) = remap(source.pos_y, source.pos_x);
+        }
+        for layer in self.data.iter_mut() {
+            layer.resize(size_w, size_h, mode);
+        }
+        for layer in self.nutrient.iter_mut().chain(&mut self.repellent).chain(&mut self.repellent_set) {
+            layer.resize(size_w, size_h, mode);
+        }
+        let walls = self.walls.take().map(|walls| match mode {
+            Resize::Crop => walls.crop(size_w, size_h, false),
+            Resize::Nearest | Resize::Bilinear => walls.rescale_nearest(size_w, size_h),
+        });
+        self.size_w = size_w;
+        self.size_h = size_h;
+        self.params = params;
+        self.restore_scratch();
+        self.set_walls(walls)?;
+        self.active_cells = (0..size_h as i32)
+            .flat_map(|y| (0..size_w as i32).map(move |x| (y, x)))
+            .filter(|&c| self.data.iter().any(|channel| channel[c].is_live()))
+            .collect();
+        Ok(())
+    }
+    /// Dab `brush` centered on world coordinates (`x`, `y`), e.g. under the pointer. Trail and
+    /// fields aren't painted on walls, and new agents don't land in them.
+    pub fn paint(&mut self, brush: &Brush, x: f64, y: f64) -> Result<(), String> {
+        brush.validate(&self.params)?;
+        if !(x.is_finite() && y.is_finite()) {
+            return Err(format!("can't paint at ({}, {})", x, y));
+        }
+        let (size_w, size_h, boundary) = (self.size_w, self.size_h, self.params.boundary);
+        let mut cells: Vec<_> = disk(y, x, brush.radius).filter_map(|c| self.visited.resolve(c, boundary)).collect();
+        // non-periodic boundaries fold cells past the edge onto ones already in the brush
+        cells.sort_unstable();
+        cells.dedup();
+        let open: Vec<_> = cells.iter().copied().filter(|&c| !self.walls.as_ref().is_some_and(|walls| walls[c])).collect();
+        let amount = if brush.erase { -brush.strength } else { brush.strength };
+        match brush.tool {
+            Tool::Trail => {
+                let channels = brush.species.map_or(0..self.data.len(), |s| s..s + 1);
+                for &c in &open {
+                    for channel in &mut self.data[channels.clone()] {
+                        channel[c] = T::round_from_f64((channel[c].to_f64() + amount).max(0.));
+                    }
+                    self.active_cells.push_back(c);
+                }
+            }
+            Tool::Nutrient | Tool::Repellent => {
+                let field = if brush.tool == Tool::Nutrient { &mut self.nutrient } else { &mut self.repellent };
+                let field = field.get_or_insert_with(|| Vec2d::new(size_w, size_h, 0.));
+                for &c in &open {
+                    field[c] = (field[c] as f64 + amount).max(0.) as f32;
+                }
+            }
+            Tool::Wall => {
+                if brush.erase && self.walls.is_none() { return Ok(()) }
+                let mut walls = self.walls.take().unwrap_or_else(|| Vec2d::new(size_w, size_h, false));
+                for &c in &cells {
+                    walls[c] = !brush.erase;
+                }
+                self.set_walls(Some(walls))?;
+                // rather than leave agents buried in it until they wander out
+                if !brush.erase {
+                    self.remove_agents(&cells, None);
+                }
+            }
+            Tool::Agents if brush.erase => self.remove_agents(&cells, brush.species),
+            Tool::Agents => {
+                for _ in 0..brush.strength.round() as usize {
+                    let hd = self.rng.sample(Uniform::new(0., PI*2.));
+                    let r = brush.radius * self.rng.gen::<f64>().sqrt();
+                    let species = match brush.species {
+                        Some(s) => s,
+                        // u32 so seeded runs match on wasm, where usize is 32 bits
+                        None => self.rng.gen_range(0..self.data.len() as u32) as usize,
+                    };
+                    let heading = self.rng.sample(Uniform::new(0., PI*2.));
+                    let pos_y = boundary.confine(y + r*hd.sin(), size_h).0;
+                    let pos_x = boundary.confine(x + r*hd.cos(), size_w).0;
+                    let cell = self.visited.resolve((pos_y.round() as i32, pos_x.round() as i32), boundary);
+                    if cell.is_some_and(|c| self.walls.as_ref().is_some_and(|walls| walls[c])) { continue }
+                    let vel = self.params.species[species].velocity;
+                    self.agents.push(Agent { pos_x, pos_y, vel, heading, species, prev: 0, sensed: Vec::new() });
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Drop the agents on any of `cells`, only those of `species` if given.
+    fn remove_agents(&mut self, cells: &[(i32, i32)], species: Option<usize>) {
+        let mut under = Vec2d::new(self.size_w, self.size_h, false);
+        for &c in cells {
+            under[c] = true;
+        }
+        let boundary = self.params.boundary;
+        self.agents.retain(|agent| {
+            let cell = under.resolve((agent.pos_y.round() as i32, agent.pos_x.round() as i32), boundary);
+            !(cell.is_some_and(|c| under[c]) && species.is_none_or(|s| s == agent.species))
+        });
+    }
+    /// Dab `brush` along the segment from `from` to `to`, both (x, y), closely enough for a
+    /// solid stroke, e.g. between two pointer positions. `from` itself isn't painted.
+    pub fn paint_line(&mut self, brush: &Brush, from: (f64, f64), to: (f64, f64)) -> Result<(), String> {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let spacing = (brush.radius / 2.).max(0.5);
+        let dabs = ((dx.hypot(dy) / spacing).ceil() as usize).max(1);
+        for i in 1..=dabs {
+            let f = i as f64 / dabs as f64;
+            self.paint(brush, from.0 + dx*f, from.1 + dy*f)?;
+        }
+        Ok(())
+    }
+    /// Swap in new parameters mid-run. The world size and species count can't change here.
+    /// Changing a species' `num_agents` brings it to that many agents straight away, spawning
+    /// the rest from `params.spawn` or dropping the newest.
+    pub fn set_params(&mut self, params: SimParams) -> Result<(), String> {
+        params.validate()?;
+        if (params.size_w, params.size_h) != (self.size_w, self.size_h) {
+            return Err(String::from("world size can't be changed with set_params, use resize"));
+        }
+        if params.species.len() != self.params.species.len() {
+            return Err(String::from("species count can't be changed with set_params"));
+        }
+        for agent in &mut self.agents {
+            agent.vel = params.species[agent.species].velocity;
+        }
+        let mut missing = params.clone();
+        for (s, (sp, old)) in missing.species.iter_mut().zip(&self.params.species).enumerate() {
+            let wanted = sp.num_agents;
+            sp.num_agents = 0;
+            if wanted == old.num_agents { continue }
+            let have = self.agents.iter().filter(|agent| agent.species == s).count();
+            if have < wanted {
+                sp.num_agents = wanted - have;
+            } else {
+                let mut seen = 0;
+                self.agents.retain(|agent| {
+                    if agent.species != s { return true }
+                    seen += 1;
+                    seen <= wanted
+                });
+            }
+        }
+        self.agents.extend(params.spawn.spawn(&missing, &mut self.rng));
+        self.params = params;
+        Ok(())
+    }
+}
+impl<T: TrailValue> Dish<T> {
+    pub fn update(&mut self, updates: u32) {
+        self.update_with(updates, &FromParams);
+    }
+    /// Like `update`, with every agent turning by `steering` instead of its species' `SteeringMode`.
+    pub fn update_with<S: Steering + ?Sized>(&mut self, _updates: u32, steering: &S) {
+        if self.repellent.is_none() && self.sources.iter().any(|source| source.repellent > 0.) {
+            self.repellent = Some(Vec2d::new(self.size_w, self.size_h, 0.));
+        }
+        let world = world(&self.params, &self.walls, &self.nutrient, &self.repellent);
+        for agent in &mut self.agents { // NTFS: probably expensive; parallelize
+            agent.update_with(&self.data, &self.params.species[agent.species], &world, steering, &mut self.rng);
+        }
+        for agent in &self.agents {
+            let amount = self.params.species[agent.species].deposit_amount;
+            for (y, x, val) in agent.deposit(self.params.deposit_mode, amount) {
+                if val <= 0. { continue }
+                let cell = self.visited.resolve((y, x), world.boundary);
+                if let Some(c) = cell.filter(|&c| !world.is_wall(c)) {
+                    self.deposit_acc[agent.species][c] += val;
+                    self.active_cells.push_back(c);
+                }
+            }
+        }
+        let mut emitted = Vec::new();
+        for source in &mut self.sources {
+            for cell in source.cells() {
+                let cell = self.visited.resolve(cell, world.boundary);
+                if let Some(c) = cell.filter(|&c| !world.is_wall(c)) {
+                    if source.strength > 0. {
+                        for acc in &mut self.deposit_acc {
+                            acc[c] += source.strength as f32;
+                        }
+                        self.active_cells.push_back(c);
+                    }
+                    if source.repellent > 0. { emitted.push((c, source.repellent as f32)) }
+                }
+            }
+            source.advance(&world, self.size_w, self.size_h);
+        }
+        if let Some(repellent) = &mut self.repellent {
+            for (c, amount) in emitted {
+                repellent[c] += amount;
+            }
+        }
+        // fold the whole step's deposits in at once, so rounding and clipping happen once
+        // per cell rather than once per agent
+        for (channel, acc) in self.data.iter_mut().zip(&mut self.deposit_acc) {
+            for c in &self.active_cells {
+                if acc[*c] > 0. {
+                    channel[*c] = T::round_from_f64(channel[*c].to_f64() + acc[*c] as f64);
+                    acc[*c] = 0.;
+                }
+            }
+        }
+        self.diffuse();
+        self.decay();
+        self.spread_repellent();
+    }
+    /// Diffuse and decay the repellent field, if any, with the trail's kernel and decay law.
+    fn spread_repellent(&mut self) {
+        let Some(repellent) = &self.repellent else { return };
+        let stencil = Stencil::new(&self.params.kernel);
+        let (mix, decay) = (self.params.diffuse_mix, self.params.decay);
+        let world = World { repellent: None, ..world(&self.params, &self.walls, &self.nutrient, &self.repellent) };
+        let separable = stencil.separable(&world);
+        let mut rows = RowPass::default();
+        if separable { rows.reset(1, self.size_w, self.size_h) }
+        let mut next = Vec2d::new(self.size_w, self.size_h, 0f32);
+        for cy in 0..self.size_h as i32 {
+            for cx in 0..self.size_w as i32 {
+                let blurred = if separable {
+                    stencil.apply_separable(std::slice::from_ref(repellent), &mut rows, 0, (cy, cx), mix, world.boundary)
+                } else {
+                    stencil.apply(repellent, cy, cx, mix, &world)
+                };
+                next[(cy, cx)] = decay.apply(blurred as f64) as f32;
+            }
+        }
+        self.repellent = Some(next);
+    }
+    /// Sum of every species' trail clamped to 0..=255; what gets drawn in single-color views.
+    pub fn total_trail(&self) -> Vec2d<u8> {
+        let mut total = Vec2d::new(self.size_w, self.size_h, 0.);
+        for channel in &self.data {
+            for (t, &v) in total.as_mut_slice().iter_mut().zip(channel.as_slice()) {
+                *t += v.to_f64();
+            }
+        }
+        let mut out = Vec2d::new(self.size_w, self.size_h, 0u8);
+        for (o, &t) in out.as_mut_slice().iter_mut().zip(total.as_slice()) {
+            *o = T::from_f64(t).to_display();
+        }
+        out
+    }
+    pub fn diffuse_nsquared(&mut self) {
+        let kernel = self.params.kernel;
+        self.diffuse_nsquared_with(&kernel);
+    }
+    pub fn diffuse_nsquared_with<K: DiffusionKernel + ?Sized>(&mut self, kernel: &K) {
+        let stencil = Stencil::new(kernel);
+        let mix = self.params.diffuse_mix;
+        let world = world(&self.params, &self.walls, &self.nutrient, &self.repellent);
+        let separable = stencil.separable(&world);
+        if separable { self.rows.reset(self.data.len(), self.size_w, self.size_h) }
+        for (ch, alt) in self.data_alt.iter_mut().enumerate() {
+            for cy in 0..self.size_h as i32 {
+                for cx in 0..self.size_w as i32 {
+                    alt[(cy, cx)] = if separable {
+                        stencil.apply_separable(&self.data, &mut self.rows, ch, (cy, cx), mix, world.boundary)
+                    } else {
+                        stencil.apply(&self.data[ch], cy, cx, mix, &world)
+                    };
+                }
+            }
+        }
+        swap(&mut self.data, &mut self.data_alt);
+    }
+    pub fn diffuse(&mut self) {
+        let kernel = self.params.kernel;
+        self.diffuse_with(&kernel);
+    }
+    /// Blur with a kernel other than the one in `params`.
+    pub fn diffuse_with<K: DiffusionKernel + ?Sized>(&mut self, kernel: &K) {
+        // SPFA style
+        self.visited.for_each(|x| *x = false); // should hopefully compile to memset: https://users.rust-lang.org/t/fastest-way-to-zero-an-array/39222
+        for alt in &mut self.data_alt {
+            alt.for_each(|x| *x = T::default());
+        }
+
+        // deposits can list a cell more than once; keep one copy so decay only hits it once
+        let visited = &mut self.visited;
+        self.active_cells.retain(|&c| !replace(&mut visited[c], true));
+        let stencil = Stencil::new(kernel);
+        let (r, mix) = (stencil.radius, self.params.diffuse_mix);
+        let world = world(&self.params, &self.walls, &self.nutrient, &self.repellent);
+        let separable = stencil.separable(&world);
+        if separable { self.rows.reset(self.data.len(), self.size_w, self.size_h) }
+        let mut active_next = VecDeque::new();
+        while let Some((cy, cx)) = self.active_cells.pop_front() {
+            // cells are shared across channels: live if any species has trail here
+            if self.data.iter().any(|channel| channel[(cy, cx)].is_live()) {
+                for y in cy-r..=cy+r {
+                    for x in cx-r..=cx+r {
+                        match self.visited.resolve((y, x), world.boundary) {
+                            Some(c) if !self.visited[c] && !world.is_wall(c) => {
+                                self.visited[c] = true;
+                                self.active_cells.push_back(c);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            let mut live = false;
+            for (ch, alt) in self.data_alt.iter_mut().enumerate() {
+                alt[(cy, cx)] = if separable {
+                    stencil.apply_separable(&self.data, &mut self.rows, ch, (cy, cx), mix, world.boundary)
+                } else {
+                    stencil.apply(&self.data[ch], cy, cx, mix, &world)
+                };
+                live |= alt[(cy, cx)].is_live();
+            }
+            if live { active_next.push_back((cy, cx)) }
+        }
+        swap(&mut self.active_cells, &mut active_next);
+        swap(&mut self.data, &mut self.data_alt);
+    }
+    pub fn decay_nsquared(&mut self) {
+        let decay = self.params.decay;
+        for channel in &mut self.data {
+            channel.for_each(|v| *v = T::from_f64(decay.apply(v.to_f64())));
+        }
+    }
+    pub fn decay(&mut self) {
+        let decay = self.params.decay;
+        for channel in &mut self.data {
+            for c in &self.active_cells {
+                channel[*c] = T::from_f64(decay.apply(channel[*c].to_f64()));
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Save and restore a full `Dish`: params, agents, trail, active cells and RNG state,
+//! so a resumed run continues exactly where the saved one left off.
+//!
+//! The binary format is `MAGIC`, then `VERSION` as a little-endian u32, then the trail value
+//! type's name as a length byte plus ASCII, then the bincode-encoded `Dish`. The JSON variant
+//! carries the same version and type in a wrapper object and is meant for debugging, not for
+//! compactness.
+
+use serde::{ Deserialize, Serialize };
+
+use crate::sim::Dish;
+use crate::trail::TrailValue;
+
+pub const MAGIC: &[u8; 4] = b"SLMD";
+/// Bump whenever a released build changes what `Dish` serializes.
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct JsonSnapshot<D> {
+    version: u32,
+    trail: String,
+    dish: D,
+}
+
+pub fn save<T: TrailValue>(dish: &Dish<T>) -> Vec<u8> {
+    let mut out = Vec::from(&MAGIC[..]);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.push(T::NAME.len() as u8);
+    out.extend_from_slice(T::NAME.as_bytes());
+    bincode::serialize_into(&mut out, dish).expect("serializing into a Vec can't fail");
+    out
+}
+
+pub fn load<T: TrailValue>(bytes: &[u8]) -> Result<Dish<T>, String> {
+    if bytes.len() < 9 || &bytes[..4] != MAGIC {
+        return Err(String::from("not a dish snapshot"));
+    }
+    let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    if version != VERSION {
+        return Err(format!("unsupported snapshot version {} (expected {})", version, VERSION));
+    }
+    let name_end = 9 + bytes[8] as usize;
+    let name = bytes.get(9..name_end).ok_or_else(|| String::from("corrupt snapshot: truncated header"))?;
+    check_trail::<T>(&String::from_utf8_lossy(name))?;
+    let dish = bincode::deserialize(&bytes[name_end..]).map_err(|e| format!("corrupt snapshot: {}", e))?;
+    finish(dish)
+}
+
+pub fn save_json<T: TrailValue>(dish: &Dish<T>) -> String {
+    serde_json::to_string_pretty(&JsonSnapshot { version: VERSION, trail: String::from(T::NAME), dish })
+        .expect("dish is always representable as JSON")
+}
+
+pub fn load_json<T: TrailValue>(json: &str) -> Result<Dish<T>, String> {
+    #[derive(Deserialize)]
+    struct Header { version: u32, trail: String }
+    let header: Header = serde_json::from_str(json).map_err(|e| format!("corrupt snapshot: {}", e))?;
+    if header.version != VERSION {
+        return Err(format!("unsupported snapshot version {} (expected {})", header.version, VERSION));
+    }
+    check_trail::<T>(&header.trail)?;
+    let snap: JsonSnapshot<Dish<T>> = serde_json::from_str(json).map_err(|e| format!("corrupt snapshot: {}", e))?;
+    finish(snap.dish)
+}
+
+fn check_trail<T: TrailValue>(name: &str) -> Result<(), String> {
+    if name == T::NAME {
+        Ok(())
+    } else {
+        Err(format!("snapshot has a {} trail, expected {}", name, T::NAME))
+    }
+}
+
+fn finish<T: TrailValue>(mut dish: Dish<T>) -> Result<Dish<T>, String> {
+    dish.params.validate()?;
+    if dish.data.len() != dish.params.species.len() {
+        return Err(String::from("snapshot needs one trail channel per species"));
+    }
+    if dish.data.iter().any(|channel| (channel.size_w, channel.size_h) != (dish.size_w, dish.size_h)
+                                      || channel.as_slice().len() != dish.size_w * dish.size_h) {
+        return Err(String::from("snapshot trail map doesn't match the world size"));
+    }
+    if dish.walls.as_ref().is_some_and(|walls| (walls.size_w, walls.size_h) != (dish.size_w, dish.size_h)
+                                                || walls.as_slice().len() != dish.size_w * dish.size_h) {
+        return Err(String::from("snapshot walls don't match the world size"));
+    }
+    if dish.nutrient.as_ref().is_some_and(|nutrient| (nutrient.size_w, nutrient.size_h) != (dish.size_w, dish.size_h)
+                                                     || nutrient.as_slice().len() != dish.size_w * dish.size_h) {
+        return Err(String::from("snapshot nutrient field doesn't match the world size"));
+    }
+    if dish.repellent.as_ref().is_some_and(|repellent| (repellent.size_w, repellent.size_h) != (dish.size_w, dish.size_h)
+                                                       || repellent.as_slice().len() != dish.size_w * dish.size_h) {
+        return Err(String::from("snapshot repellent field doesn't match the world size"));
+    }
+    for source in &dish.sources {
+        source.validate()?;
+    }
+    if dish.agents.iter().any(|agent| agent.species >= dish.data.len()) {
+        return Err(String::from("snapshot agent refers to a missing species"));
+    }
+    dish.restore_scratch();
+    Ok(dish)
+}
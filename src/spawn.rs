@@ -0,0 +1,192 @@
+use std::f64::consts::PI;
+
+use rand::distributions::{ Distribution, Uniform };
+use rand::Rng;
+use rand_distr::Normal;
+use serde::{ Deserialize, Serialize };
+
+use crate::params::SimParams;
+use crate::sim::Agent;
+use crate::vec2d::Vec2d;
+
+/// Which way agents placed around a center start out heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Facing {
+    Inward,
+    Outward,
+    /// Counterclockwise along the circle through the agent.
+    Tangent,
+    Random,
+}
+
+/// Where agents start out and which way they head. Centers are fractions of the world's width
+/// and height and radii fractions of its shorter side, rounded down to whole cells, so a pattern
+/// carries over between world sizes. Agents landing outside the world are brought back in
+/// according to the boundary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SpawnPattern {
+    /// Anywhere, heading anywhere.
+    Uniform,
+    /// Spread evenly over a disk.
+    Disk { x: f64, y: f64, radius: f64, facing: Facing },
+    /// On a circle.
+    Ring { x: f64, y: f64, radius: f64, facing: Facing },
+    /// All on one spot, heading out every which way.
+    Point { x: f64, y: f64 },
+    /// Normally distributed around a center, heading anywhere; `sigma` is a fraction of the
+    /// shorter side.
+    Gaussian { x: f64, y: f64, sigma: f64 },
+    /// Anywhere, with each cell as likely as its value in this world-sized density map, e.g.
+    /// `image.map(|&v| v as f32)`.
+    Image(Vec2d<f32>),
+    /// Explicit `(x, y, heading)` in cells and radians, handed out in order and started over
+    /// when there are more agents than entries.
+    List(Vec<(f64, f64, f64)>),
+}
+impl Default for SpawnPattern {
+    fn default() -> Self {
+        SpawnPattern::Ring { x: 0.25, y: 0.25, radius: 0.2, facing: Facing::Tangent }
+    }
+}
+impl SpawnPattern {
+    pub fn validate(&self, size_w: usize, size_h: usize) -> Result<(), String> {
+        let ok = match self {
+            SpawnPattern::Uniform => true,
+            SpawnPattern::Disk { x, y, radius, .. } | SpawnPattern::Ring { x, y, radius, .. } =>
+                x.is_finite() && y.is_finite() && radius.is_finite() && *radius >= 0.,
+            SpawnPattern::Point { x, y } => x.is_finite() && y.is_finite(),
+            SpawnPattern::Gaussian { x, y, sigma } => x.is_finite() && y.is_finite() && sigma.is_finite() && *sigma > 0.,
+            SpawnPattern::Image(density) => {
+                if (density.size_w, density.size_h) != (size_w, size_h) {
+                    return Err(format!("spawn density map is {}x{} but the world is {}x{}",
+                                       density.size_w, density.size_h, size_w, size_h));
+                }
+                density.as_slice().iter().all(|v| v.is_finite() && *v >= 0.) && density.as_slice().iter().any(|&v| v > 0.)
+            }
+            SpawnPattern::List(list) => !list.is_empty() && list.iter().all(|(x, y, h)| x.is_finite() && y.is_finite() && h.is_finite()),
+        };
+        if ok { Ok(()) } else { Err(format!("invalid spawn pattern {:?}", self.name())) }
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            SpawnPattern::Uniform => "uniform",
+            SpawnPattern::Disk { .. } => "disk",
+            SpawnPattern::Ring { .. } => "ring",
+            SpawnPattern::Point { .. } => "point",
+            SpawnPattern::Gaussian { .. } => "gaussian",
+            SpawnPattern::Image(_) => "image",
+            SpawnPattern::List(_) => "list",
+        }
+    }
+    /// Switch to the pattern called `name` (uniform, disk, ring, point, gaussian), keeping the
+    /// current center, radius and facing where both have them.
+    pub(crate) fn set_kind(&mut self, name: &str) -> Result<(), String> {
+        let (x, y, radius, facing) = self.parts();
+        *self = match name {
+            "uniform"  => SpawnPattern::Uniform,
+            "disk"     => SpawnPattern::Disk { x, y, radius, facing },
+            "ring"     => SpawnPattern::Ring { x, y, radius, facing },
+            "point"    => SpawnPattern::Point { x, y },
+            "gaussian" => SpawnPattern::Gaussian { x, y, sigma: radius },
+            _ => return Err(format!("bad value `{}` for `spawn`", name)),
+        };
+        Ok(())
+    }
+    /// Set `spawn_x`, `spawn_y`, `spawn_radius` (the sigma of a gaussian) or, with a facing
+    /// name, `spawn_facing`, on patterns that have it.
+    pub(crate) fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let bad = || format!("bad value `{}` for `{}`", value, name);
+        let kind = self.name();
+        let missing = || format!("`{}` doesn't apply to the {} spawn pattern", name, kind);
+        let (x, y, radius, facing) = match self {
+            SpawnPattern::Disk { x, y, radius, facing } | SpawnPattern::Ring { x, y, radius, facing } =>
+                (x, y, Some(radius), Some(facing)),
+            SpawnPattern::Point { x, y } => (x, y, None, None),
+            SpawnPattern::Gaussian { x, y, sigma } => (x, y, Some(sigma), None),
+            _ => return Err(missing()),
+        };
+        match name {
+            "spawn_x"      => *x = value.parse().map_err(|_| bad())?,
+            "spawn_y"      => *y = value.parse().map_err(|_| bad())?,
+            "spawn_radius" => *radius.ok_or_else(missing)? = value.parse().map_err(|_| bad())?,
+            "spawn_facing" => *facing.ok_or_else(missing)? = match value {
+                "inward"  => Facing::Inward,
+                "outward" => Facing::Outward,
+                "tangent" => Facing::Tangent,
+                "random"  => Facing::Random,
+                _ => return Err(bad()),
+            },
+            _ => return Err(format!("unknown parameter `{}`", name)),
+        }
+        Ok(())
+    }
+    fn parts(&self) -> (f64, f64, f64, Facing) {
+        match *self {
+            SpawnPattern::Disk { x, y, radius, facing } | SpawnPattern::Ring { x, y, radius, facing } => (x, y, radius, facing),
+            SpawnPattern::Point { x, y } => (x, y, 0.2, Facing::Random),
+            SpawnPattern::Gaussian { x, y, sigma } => (x, y, sigma, Facing::Random),
+            _ => (0.5, 0.5, 0.2, Facing::Random),
+        }
+    }
+    /// `params.species[s].num_agents` agents of each species `s` in turn, placed in a world
+    /// shaped by `params`.
+    pub fn spawn<R: Rng + ?Sized>(&self, params: &SimParams, rng: &mut R) -> Vec<Agent> {
+        let (size_w, size_h) = (params.size_w as f64, params.size_h as f64);
+        let shorter = size_w.min(size_h);
+        let dist_hd = Uniform::from(0f64..PI*2.);
+        let cumulative: Vec<f64> = match self {
+            SpawnPattern::Image(density) => density.as_slice().iter()
+                .scan(0., |total, &v| { *total += v as f64; Some(*total) })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let facing = |facing: Facing, hd: f64, rng: &mut R| match facing {
+            Facing::Inward  => (hd + PI).rem_euclid(PI*2.),
+            Facing::Outward => hd,
+            Facing::Tangent => (hd + PI/2.).rem_euclid(PI*2.),
+            Facing::Random  => dist_hd.sample(rng),
+        };
+
+        let placements = params.species.iter().enumerate()
+            .flat_map(|(species, sp)| std::iter::repeat_n((species, sp.velocity), sp.num_agents));
+        placements.enumerate().map(|(i, (species, vel))| {
+            let (y, x, heading) = match *self {
+                SpawnPattern::Uniform => {
+                    let (y, x) = (rng.gen_range(0. ..size_h), rng.gen_range(0. ..size_w));
+                    (y, x, dist_hd.sample(rng))
+                }
+                SpawnPattern::Disk { x, y, radius, facing: f } => {
+                    let (hd, r) = (dist_hd.sample(rng), (radius * shorter).floor() * rng.gen::<f64>().sqrt());
+                    (r*hd.sin() + size_h*y, r*hd.cos() + size_w*x, facing(f, hd, rng))
+                }
+                SpawnPattern::Ring { x, y, radius, facing: f } => {
+                    let (hd, r) = (dist_hd.sample(rng), (radius * shorter).floor());
+                    (r*hd.sin() + size_h*y, r*hd.cos() + size_w*x, facing(f, hd, rng))
+                }
+                SpawnPattern::Point { x, y } => (size_h*y, size_w*x, dist_hd.sample(rng)),
+                SpawnPattern::Gaussian { x, y, sigma } => {
+                    let normal = Normal::new(0., sigma * shorter).expect("validated sigma");
+                    (size_h*y + normal.sample(rng), size_w*x + normal.sample(rng), dist_hd.sample(rng))
+                }
+                SpawnPattern::Image(ref density) => {
+                    let pick = rng.gen_range(0. ..*cumulative.last().expect("validated density map"));
+                    let c = cumulative.partition_point(|&total| total <= pick).min(cumulative.len() - 1);
+                    let (cy, cx) = ((c / density.size_w) as f64, (c % density.size_w) as f64);
+                    (cy + rng.gen_range(-0.5..0.5), cx + rng.gen_range(-0.5..0.5), dist_hd.sample(rng))
+                }
+                SpawnPattern::List(ref list) => {
+                    let (x, y, heading) = list[i % list.len()];
+                    (y, x, heading)
+                }
+            };
+            Agent {
+                pos_y: params.boundary.confine(y, params.size_h).0,
+                pos_x: params.boundary.confine(x, params.size_w).0,
+                vel,
+                heading,
+                species,
+                prev: 0, sensed: Vec::new(),
+            }
+        }).collect()
+    }
+}
@@ -0,0 +1,78 @@
+use rand::distributions::Uniform;
+use rand::{ Rng, RngCore };
+use serde::{ Deserialize, Serialize };
+
+use crate::params::SpeciesParams;
+use crate::sim::Agent;
+
+/// Decides how an agent turns each step. Implement this to try other behaviours and run them
+/// with `Dish::update_with`; the built-in rules are the variants of `SteeringMode`.
+pub trait Steering {
+    /// Heading change in radians (positive turns toward positive sensor angles) given this
+    /// step's readings; `sensed[i]` is from the sensor at `params.sensor_offset(i)`. `agent`
+    /// still has its pre-turn heading and position; `params` are its species'.
+    fn steer(&self, sensed: &[f64], agent: &Agent, params: &SpeciesParams, rng: &mut dyn RngCore) -> f64;
+}
+
+/// How an agent turns given its sensor readings. `Weighted` and `Jones` are three-sensor rules:
+/// with other arrays, `lef` and `rig` are the strongest sensors on either side and `fwd` the one
+/// pointing straight ahead, with missing ones never winning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SteeringMode {
+    /// When forward is weakest, turn left with probability lef / (lef + rig); ties don't turn.
+    Weighted,
+    /// Jones (2010): keep going if forward is strongest, turn left or right at random if it's
+    /// weakest, otherwise toward the stronger side, and turn at random if all three are equal.
+    Jones,
+    /// Turn toward the mean sensor direction weighted by how far each reading is above the
+    /// weakest one, by at most `turn_angle`. Uses every sensor, including rear-facing ones.
+    Gradient,
+}
+impl Steering for SteeringMode {
+    fn steer(&self, sensed: &[f64], _agent: &Agent, params: &SpeciesParams, rng: &mut dyn RngCore) -> f64 {
+        let turn = params.turn_angle;
+        match self {
+            SteeringMode::Weighted => {
+                let ([lef, fwd, rig], rand) = (sides(sensed, params), rng.sample(Uniform::new(0., 1.)));
+                if      fwd > lef && fwd > rig { 0. }
+                else if fwd < lef && fwd < rig {
+                    let p_lef = if lef + rig > 0. { lef / (lef + rig) } else { 0.5 }; // repellent channels can go negative
+                    if rand < p_lef { turn } else { -turn }
+                }
+                else if lef > rig { turn }
+                else if rig > lef { -turn }
+                else { 0. }
+            }
+            SteeringMode::Jones => {
+                let ([lef, fwd, rig], rand) = (sides(sensed, params), rng.sample(Uniform::new(0., 1.)));
+                if      fwd > lef && fwd > rig { 0. }
+                else if fwd < lef && fwd < rig || lef == rig { // forward weakest, or all equal
+                    if rand < 0.5 { turn } else { -turn }
+                }
+                else if lef > rig { turn }
+                else { -turn }
+            }
+            SteeringMode::Gradient => {
+                let weakest = sensed.iter().copied().fold(f64::INFINITY, f64::min);
+                let (mut sy, mut sx) = (0., 0.);
+                for (i, &v) in sensed.iter().enumerate() {
+                    let angle = params.sensor_offset(i);
+                    sy += (v - weakest) * angle.sin();
+                    sx += (v - weakest) * angle.cos();
+                }
+                if sy == 0. && sx == 0. { 0. } else { sy.atan2(sx).clamp(-turn, turn) }
+            }
+        }
+    }
+}
+
+/// Strongest reading at positive, zero and negative angles.
+fn sides(sensed: &[f64], params: &SpeciesParams) -> [f64; 3] {
+    let mut sides = [f64::NEG_INFINITY; 3];
+    for (i, &v) in sensed.iter().enumerate() {
+        let angle = params.sensor_offset(i);
+        let side = if angle > 0. { 0 } else if angle == 0. { 1 } else { 2 };
+        sides[side] = sides[side].max(v);
+    }
+    sides
+}
@@ -0,0 +1,51 @@
+use std::fmt::Debug;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A value type the trail map can be stored as. u8 is fastest and what the demo uses;
+/// u16 gives more headroom; f32 keeps faint trails and doesn't truncate on diffuse/decay.
+pub trait TrailValue: Copy + Default + PartialOrd + Debug + Serialize + DeserializeOwned + 'static {
+    /// Stored in snapshots so they can't be loaded as the wrong type.
+    const NAME: &'static str;
+
+    fn to_f64(self) -> f64;
+    /// Saturating, truncating conversion (same as `as` for the integer types).
+    fn from_f64(v: f64) -> Self;
+    /// Nearest representable value; used when folding deposits in.
+    fn round_from_f64(v: f64) -> Self;
+
+    fn is_live(self) -> bool {
+        self > Self::default()
+    }
+    /// Clamped into 0..=255 for drawing and image export.
+    fn to_display(self) -> u8 {
+        self.to_f64() as u8
+    }
+}
+
+impl TrailValue for u8 {
+    const NAME: &'static str = "u8";
+    fn to_f64(self) -> f64 { self as f64 }
+    fn from_f64(v: f64) -> Self { v as u8 }
+    fn round_from_f64(v: f64) -> Self { v.round() as u8 }
+    fn to_display(self) -> u8 { self }
+}
+
+impl TrailValue for u16 {
+    const NAME: &'static str = "u16";
+    fn to_f64(self) -> f64 { self as f64 }
+    fn from_f64(v: f64) -> Self { v as u16 }
+    fn round_from_f64(v: f64) -> Self { v.round() as u16 }
+}
+
+/// Below this an f32 cell counts as empty, otherwise exponential decay would never let the
+/// sparse diffusion drop a cell and the active set would grow to the whole dish.
+pub const F32_FLUSH: f64 = 1e-4;
+
+impl TrailValue for f32 {
+    const NAME: &'static str = "f32";
+    fn to_f64(self) -> f64 { self as f64 }
+    fn from_f64(v: f64) -> Self { if v < F32_FLUSH { 0. } else { v as f32 } }
+    fn round_from_f64(v: f64) -> Self { Self::from_f64(v) }
+}
@@ -0,0 +1,172 @@
+use core::ops::{ Index, IndexMut };
+
+use serde::{ Deserialize, Serialize };
+
+use crate::trail::TrailValue;
+
+/// What happens at the edges of the world, for trail lookups and agent movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Boundary {
+    Periodic,   // a torus: leaving one edge enters the opposite one
+    Reflective, // walls: lookups mirror back in, agents bounce
+    Absorbing,  // lookups past the edge read empty, so trail drains out; agents bounce
+    Clamped,    // lookups repeat the edge cell, agents stop at the edge
+}
+impl Boundary {
+    /// Index `i` into a dimension of `n` cells, or `None` if it's past an absorbing edge.
+    pub fn resolve(self, i: i32, n: i32) -> Option<i32> {
+        if (0..n).contains(&i) {
+            return Some(i);
+        }
+        match self {
+            Boundary::Periodic => Some(i.rem_euclid(n)),
+            Boundary::Reflective => {
+                let m = i.rem_euclid(2 * n);
+                Some(if m < n { m } else { 2 * n - 1 - m })
+            }
+            Boundary::Absorbing => None,
+            Boundary::Clamped => Some(i.clamp(0, n - 1)),
+        }
+    }
+    /// Agent coordinate `p` brought back into a dimension of `n` cells, and whether it bounced
+    /// off a wall (so the heading should be mirrored).
+    pub fn confine(self, p: f64, n: usize) -> (f64, bool) {
+        let max = (n - 1) as f64;
+        match self {
+            Boundary::Periodic => (p.rem_euclid(n as f64), false),
+            Boundary::Reflective | Boundary::Absorbing => {
+                if p < 0. {
+                    ((-p).min(max), true)
+                } else if p > max {
+                    ((2. * max - p).max(0.), true)
+                } else {
+                    (p, false)
+                }
+            }
+            Boundary::Clamped => (p.clamp(0., max), false),
+        }
+    }
+}
+
+/// How `Vec2d::resize` carries a grid over to a new size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resize {
+    Crop,       // keep the top-left corner in place, cutting off or padding with empty cells
+    Nearest,    // stretch, each new cell copying the old one under its center
+    Bilinear,   // stretch, interpolating between the four old cells around each new center
+}
+impl Resize {
+    /// Where coordinate `p` along a dimension of `old` cells ends up in one of `new` cells.
+    pub fn remap(self, p: f64, old: usize, new: usize) -> f64 {
+        match self {
+            Resize::Crop => p,
+            Resize::Nearest | Resize::Bilinear => (p + 0.5) * new as f64 / old as f64 - 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Vec2d<T: Clone> {
+    pub size_w: usize,
+    pub size_h: usize,
+    data: Vec<T>
+}
+impl<T: Clone> Vec2d<T> {
+    pub fn new(size_w: usize, size_h: usize, fill: T) -> Vec2d<T> {
+        Vec2d { size_w, size_h, data: vec![fill; size_h * size_w] }
+    }
+    /// Cut off or pad to the new size keeping the top-left corner in place; new cells are `fill`.
+    pub fn crop(&self, size_w: usize, size_h: usize, fill: T) -> Vec2d<T> {
+        let mut out = Vec2d::new(size_w, size_h, fill);
+        for y in 0..size_h.min(self.size_h) {
+            let w = size_w.min(self.size_w);
+            out.data[y * size_w..y * size_w + w].clone_from_slice(&self.data[y * self.size_w..y * self.size_w + w]);
+        }
+        out
+    }
+    /// Stretch to the new size, each new cell copying the old one under its center.
+    pub fn rescale_nearest(&self, size_w: usize, size_h: usize) -> Vec2d<T> {
+        let source = |i: usize, old: usize, new: usize| ((i * 2 + 1) * old / (new * 2)).min(old - 1);
+        let mut data = Vec::with_capacity(size_w * size_h);
+        for y in 0..size_h {
+            let row = source(y, self.size_h, size_h) * self.size_w;
+            data.extend((0..size_w).map(|x| self.data[row + source(x, self.size_w, size_w)].clone()));
+        }
+        Vec2d { size_w, size_h, data }
+    }
+    pub fn for_each<F>(&mut self, f: F) where F: FnMut(&mut T) {
+        self.data.iter_mut().for_each(f);
+    }
+    /// `index` with out-of-range coordinates handled by `boundary`; `None` past absorbing edges.
+    pub fn resolve(&self, index: (i32, i32), boundary: Boundary) -> Option<(i32, i32)> {
+        Some((boundary.resolve(index.0, self.size_h as i32)?, boundary.resolve(index.1, self.size_w as i32)?))
+    }
+    pub fn get(&self, index: (i32, i32), boundary: Boundary) -> Option<&T> {
+        self.resolve(index, boundary).map(|c| &self[c])
+    }
+    pub fn map<U: Clone, F>(&self, f: F) -> Vec2d<U> where F: FnMut(&T) -> U {
+        Vec2d { size_w: self.size_w, size_h: self.size_h, data: self.data.iter().map(f).collect() }
+    }
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+}
+
+impl<T: TrailValue> Vec2d<T> {
+    /// Change size in place; `Resize::Crop` pads with empty cells.
+    pub fn resize(&mut self, size_w: usize, size_h: usize, mode: Resize) {
+        *self = match mode {
+            Resize::Crop => self.crop(size_w, size_h, T::default()),
+            Resize::Nearest => self.rescale_nearest(size_w, size_h),
+            Resize::Bilinear => {
+                let mut out = Vec2d::new(size_w, size_h, T::default());
+                for y in 0..size_h {
+                    let (y0, y1, fy) = lerp_taps(Resize::Bilinear.remap(y as f64, size_h, self.size_h), self.size_h);
+                    for x in 0..size_w {
+                        let (x0, x1, fx) = lerp_taps(Resize::Bilinear.remap(x as f64, size_w, self.size_w), self.size_w);
+                        let at = |y: usize, x: usize| self.data[y * self.size_w + x].to_f64();
+                        let top = at(y0, x0) * (1. - fx) + at(y0, x1) * fx;
+                        let bottom = at(y1, x0) * (1. - fx) + at(y1, x1) * fx;
+                        out.data[y * size_w + x] = T::round_from_f64(top * (1. - fy) + bottom * fy);
+                    }
+                }
+                out
+            }
+        };
+    }
+}
+
+/// The two cells around coordinate `p` in a dimension of `n`, clamped to the edges, and how
+/// far `p` is from the first toward the second.
+fn lerp_taps(p: f64, n: usize) -> (usize, usize, f64) {
+    let p = p.clamp(0., (n - 1) as f64);
+    let i = p.floor() as usize;
+    (i, (i + 1).min(n - 1), p - i as f64)
+}
+
+// TODO: https://stackoverflow.com/questions/57203009/implementing-slice-for-custom-type (for iter_mut)
+impl<T: Clone> Index<(i32, i32)> for Vec2d<T> {
+    type Output = T;
+    fn index(&self, index: (i32, i32)) -> &Self::Output {
+        &self.data[index.0.rem_euclid(self.size_h as i32) as usize * self.size_w
+                 + index.1.rem_euclid(self.size_w as i32) as usize]
+    }
+}
+impl<T: Clone> IndexMut<(i32, i32)> for Vec2d<T> {
+    fn index_mut(&mut self, index: (i32, i32)) -> &mut Self::Output {
+        &mut self.data[index.0.rem_euclid(self.size_h as i32) as usize * self.size_w
+                     + index.1.rem_euclid(self.size_w as i32) as usize]
+    }
+}
+
+impl<T: Clone> IntoIterator for Vec2d<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
+}
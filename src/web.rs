@@ -0,0 +1,151 @@
+use wasm_bindgen::{ Clamped, JsCast };
+use web_sys::{ WebGlBuffer, WebGlProgram, WebGlRenderingContext, WebGlShader, WebGlTexture, WebGlUniformLocation };
+
+use crate::gl::{ Gl, GlRenderer };
+use crate::palette::Palette;
+use crate::render::Frame;
+use crate::sim::Dish;
+use crate::vec2d::Resize;
+
+/// Browser front end: owns the canvas and draws a `Dish` onto it.
+pub struct WebDish {
+    canvas: web_sys::HtmlCanvasElement,
+    frame: Frame,
+    gl: Option<GlRenderer<WebGlRenderingContext>>,
+}
+impl WebDish {
+    pub fn new(canvas: web_sys::HtmlCanvasElement) -> WebDish {
+        WebDish { canvas, frame: Frame::new(), gl: None }
+    }
+    /// Look up a `<canvas>` by its element id.
+    pub fn by_id(id: &str) -> Result<WebDish, String> {
+        let document = web_sys::window().and_then(|w| w.document()).ok_or("no document")?;
+        let canvas = document.get_element_by_id(id).ok_or_else(|| format!("no element with id `{}`", id))?
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .map_err(|_| format!("`{}` isn't a canvas", id))?;
+        Ok(WebDish::new(canvas))
+    }
+    pub fn canvas(&self) -> &web_sys::HtmlCanvasElement {
+        &self.canvas
+    }
+    /// World coordinates (x, y) under a point `offset_x`, `offset_y` CSS pixels from the
+    /// canvas' top left, e.g. a pointer event's offset.
+    pub fn cell_at(&self, dish: &Dish, offset_x: f64, offset_y: f64) -> (f64, f64) {
+        let width = self.canvas.client_width().max(1) as f64;
+        let height = self.canvas.client_height().max(1) as f64;
+        (offset_x / width * dish.size_w as f64 - 0.5, offset_y / height * dish.size_h as f64 - 0.5)
+    }
+    /// Resize `dish` to the canvas' on-screen size at `cell_size` CSS pixels per cell, and
+    /// the canvas' drawing buffer to match. Call on window resize.
+    pub fn fit_canvas(&mut self, dish: &mut Dish, cell_size: f64, mode: Resize) -> Result<(), String> {
+        let size_w = (self.canvas.client_width() as f64 / cell_size) as usize;
+        let size_h = (self.canvas.client_height() as f64 / cell_size) as usize;
+        if (size_w, size_h) != (dish.size_w, dish.size_h) {
+            dish.resize(size_w, size_h, mode)?;
+        }
+        self.canvas.set_width(size_w as u32);
+        self.canvas.set_height(size_h as u32);
+        Ok(())
+    }
+    /// Color the trail with `palette` from the next frame on, in either renderer.
+    pub fn set_palette(&mut self, palette: Palette) -> Result<(), String> {
+        palette.validate()?;
+        if let Some(gl) = &mut self.gl {
+            gl.set_palette(palette.clone())?;
+        }
+        self.frame.set_palette(palette);
+        Ok(())
+    }
+    /// Draw the trail map one cell per canvas pixel, in a single `put_image_data`.
+    pub fn render(&mut self, dish: &Dish) {
+        let ctx = self.canvas
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()
+            .unwrap();
+
+        let pixels = self.frame.draw(dish);
+        let image = web_sys::ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(pixels), dish.size_w as u32, dish.size_h as u32).expect("couldn't create image data");
+        ctx.put_image_data(&image, 0., 0.).expect("couldn't draw image data");
+    }
+    /// Draw the trail map with WebGL instead, through a `GlRenderer` set up on first use.
+    /// A canvas can only have one kind of context, so don't mix this with `render`.
+    pub fn render_webgl(&mut self, dish: &Dish) -> Result<(), String> {
+        if self.gl.is_none() {
+            let ctx = self.canvas
+                .get_context("webgl")
+                .map_err(|_| String::from("couldn't get a webgl context"))?
+                .ok_or("webgl isn't available")?
+                .dyn_into::<WebGlRenderingContext>()
+                .map_err(|_| String::from("not a webgl context"))?;
+            let mut gl = GlRenderer::new(ctx)?;
+            gl.set_palette(self.frame.palette().clone())?;
+            self.gl = Some(gl);
+        }
+        let (width, height) = (self.canvas.width() as i32, self.canvas.height() as i32);
+        self.gl.as_mut().expect("set up above").draw(dish, width, height)
+    }
+}
+
+impl Gl for WebGlRenderingContext {
+    type Shader = WebGlShader;
+    type Program = WebGlProgram;
+    type Buffer = WebGlBuffer;
+    type Texture = WebGlTexture;
+    type Location = WebGlUniformLocation;
+
+    fn create_shader(&self, kind: u32) -> Option<WebGlShader> { self.create_shader(kind) }
+    fn shader_source(&self, shader: &WebGlShader, source: &str) { self.shader_source(shader, source) }
+    fn compile_shader(&self, shader: &WebGlShader) { self.compile_shader(shader) }
+    fn shader_compiled(&self, shader: &WebGlShader) -> bool {
+        self.get_shader_parameter(shader, WebGlRenderingContext::COMPILE_STATUS).as_bool().unwrap_or(false)
+    }
+    fn shader_info_log(&self, shader: &WebGlShader) -> Option<String> { self.get_shader_info_log(shader) }
+
+    fn create_program(&self) -> Option<WebGlProgram> { self.create_program() }
+    fn attach_shader(&self, program: &WebGlProgram, shader: &WebGlShader) { self.attach_shader(program, shader) }
+    fn link_program(&self, program: &WebGlProgram) { self.link_program(program) }
+    fn program_linked(&self, program: &WebGlProgram) -> bool {
+        self.get_program_parameter(program, WebGlRenderingContext::LINK_STATUS).as_bool().unwrap_or(false)
+    }
+    fn program_info_log(&self, program: &WebGlProgram) -> Option<String> { self.get_program_info_log(program) }
+    fn use_program(&self, program: Option<&WebGlProgram>) { self.use_program(program) }
+    fn get_attrib_location(&self, program: &WebGlProgram, name: &str) -> i32 { self.get_attrib_location(program, name) }
+    fn get_uniform_location(&self, program: &WebGlProgram, name: &str) -> Option<WebGlUniformLocation> {
+        self.get_uniform_location(program, name)
+    }
+    fn uniform1i(&self, location: Option<&WebGlUniformLocation>, x: i32) { self.uniform1i(location, x) }
+
+    fn create_buffer(&self) -> Option<WebGlBuffer> { self.create_buffer() }
+    fn bind_buffer(&self, target: u32, buffer: Option<&WebGlBuffer>) { self.bind_buffer(target, buffer) }
+    fn buffer_data_f32(&self, target: u32, data: &[f32], usage: u32) {
+        // `view` points straight into wasm memory, so nothing may allocate until it's dropped
+        unsafe {
+            let array = js_sys::Float32Array::view(data);
+            self.buffer_data_with_array_buffer_view(target, &array, usage);
+        }
+    }
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, kind: u32, normalized: bool, stride: i32, offset: i32) {
+        self.vertex_attrib_pointer_with_i32(index, size, kind, normalized, stride, offset)
+    }
+    fn enable_vertex_attrib_array(&self, index: u32) { self.enable_vertex_attrib_array(index) }
+
+    fn create_texture(&self) -> Option<WebGlTexture> { self.create_texture() }
+    fn active_texture(&self, unit: u32) { self.active_texture(unit) }
+    fn bind_texture(&self, target: u32, texture: Option<&WebGlTexture>) { self.bind_texture(target, texture) }
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32) { self.tex_parameteri(target, pname, param) }
+    fn pixel_storei(&self, pname: u32, param: i32) { self.pixel_storei(pname, param) }
+    fn tex_image_2d(&self, target: u32, level: i32, internal_format: i32, width: i32, height: i32,
+                    format: u32, kind: u32, pixels: &[u8]) -> Result<(), String> {
+        self.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            target, level, internal_format, width, height, 0, format, kind, Some(pixels))
+            .map_err(|e| format!("couldn't upload texture: {:?}", e))
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32) { self.viewport(x, y, width, height) }
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32) { self.clear_color(r, g, b, a) }
+    fn clear(&self, mask: u32) { self.clear(mask) }
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) { self.draw_arrays(mode, first, count) }
+}
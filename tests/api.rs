@@ -0,0 +1,102 @@
+use rust_webpack_template::api::{ Renderer, Simulation };
+use rust_webpack_template::palette::{ Colormap, Intensity, Palette };
+use rust_webpack_template::params::SimParams;
+use rust_webpack_template::sim::Dish;
+use rust_webpack_template::vec2d::Resize;
+
+const OPTIONS: &str = r#"{ "seed": 7, "params": { "size_w": 40, "size_h": 30, "num_agents": 200, "boundary": "reflective" } }"#;
+
+#[test]
+fn options_configure_the_dish() {
+    let sim = Simulation::from_json(OPTIONS).unwrap();
+    assert_eq!((sim.width(), sim.height(), sim.updates()), (40, 30, 0));
+    let mut params = SimParams { size_w: 40, size_h: 30, ..SimParams::default() };
+    params.set("num_agents", 200.).unwrap();
+    params.set_str("boundary", "reflective").unwrap();
+    assert_eq!(sim.dish().params, params);
+    assert_eq!(sim.dish().agents, Dish::new(params, 7).unwrap().agents);
+
+    assert!(Simulation::from_json(r#"{ "sede": 7 }"#).is_err());
+    assert!(Simulation::from_json(r#"{ "params": { "boundary": "sideways" } }"#).is_err());
+    assert!(Simulation::from_json(r#"{ "params": { "decay": [1] } }"#).is_err());
+    assert!(Simulation::from_json(r#"{ "cell_size": 0 }"#).is_err());
+    assert!(Simulation::from_json(r#"{ "params": { "size_w": 1e6, "size_h": 1e6 } }"#).is_err());
+    assert!(Simulation::from_json("{}").is_ok());
+}
+
+#[test]
+fn step_matches_dish_updates() {
+    let mut sim = Simulation::from_json(OPTIONS).unwrap();
+    let mut dish = Dish::new(sim.dish().params.clone(), 7).unwrap();
+    sim.step(5);
+    // pausing only holds back the running loop
+    sim.pause();
+    sim.step(5);
+    assert!(sim.paused());
+    for i in 0..10 {
+        dish.update(i);
+    }
+    assert_eq!(sim.updates(), 10);
+    assert_eq!(sim.dish().total_trail(), dish.total_trail());
+    sim.resume();
+    assert!(!sim.paused());
+
+    sim.reset();
+    assert_eq!(sim.updates(), 0);
+    assert_eq!(sim.dish().agents, Dish::new(dish.params.clone(), 7).unwrap().agents);
+    assert!(sim.dish().total_trail().as_slice().iter().all(|&v| v == 0));
+}
+
+#[test]
+fn set_param_by_name() {
+    let mut sim = Simulation::from_json(OPTIONS).unwrap();
+    sim.set_param("sensor_angle", 0.5).unwrap();
+    sim.set_param_str("deposit_mode", "bilinear").unwrap();
+    assert_eq!(sim.dish().params.species[0].sensor_angle, 0.5);
+    assert!(sim.set_param("no_such_thing", 1.).is_err());
+    assert!(sim.set_param("decay", f64::NAN).is_err());
+    assert!(sim.set_param("size_w", 80.).is_err());
+    assert!(sim.set_param("species", 1e5).is_err());
+    assert!(sim.set_param("num_agents", 1e12).is_err());
+    // a rejected value leaves the parameters as they were
+    assert_eq!(sim.dish().params.decay, SimParams::default().decay);
+}
+
+#[test]
+fn paint_with_the_brush() {
+    let mut sim = Simulation::from_json(OPTIONS).unwrap();
+    assert!(sim.paint(5., 5.).is_err());
+    assert!(sim.set_brush("glitter", 2., 10., false, None).is_err());
+    assert!(sim.set_brush("trail", 2., 10., false, Some(1)).is_err());
+    assert!(sim.set_brush("trail", 20000., 10., false, None).is_err());
+    sim.set_brush("wall", 2., 0., false, None).unwrap();
+    sim.paint(5., 5.).unwrap();
+    assert!(sim.dish().walls.as_ref().unwrap()[(5, 5)]);
+    sim.clear_brush();
+    assert!(sim.paint(5., 5.).is_err());
+}
+
+#[test]
+fn canvas_options() {
+    let sim = Simulation::from_json(r#"{ "renderer": "webgl", "palette": { "colormap": "magma", "gamma": 0.5 } }"#).unwrap();
+    assert_eq!(sim.renderer(), Renderer::WebGl);
+    assert_eq!(*sim.palette(), Palette { colormap: Colormap::Magma, intensity: Intensity::Gamma(0.5), ..Palette::default() });
+
+    let mut sim = Simulation::from_json(OPTIONS).unwrap();
+    assert_eq!(sim.renderer(), Renderer::Canvas2d);
+    assert_eq!(*sim.palette(), Palette::default());
+    sim.set_palette_json(r##"{ "log": true, "species_colors": ["#ff0000", "#00ffff"] }"##).unwrap();
+    assert_eq!(*sim.palette(), Palette { intensity: Intensity::Log, species_colors: vec![[255, 0, 0], [0, 255, 255]],
+                                         ..Palette::default() });
+    assert!(sim.set_palette_json(r#"{ "gamma": 0.5, "log": true }"#).is_err());
+    assert!(sim.set_palette_json(r#"{ "colormap": "plaid" }"#).is_err());
+    assert!(sim.set_palette_json(r#"{ "gamma": -1 }"#).is_err());
+    // a rejected palette leaves the current one
+    assert_eq!(sim.palette().intensity, Intensity::Log);
+    assert!(Simulation::from_json(r#"{ "renderer": "3d" }"#).is_err());
+
+    assert_eq!(sim.resize_mode(), Resize::Bilinear);
+    let sim = Simulation::from_json(r#"{ "resize": "crop" }"#).unwrap();
+    assert_eq!(sim.resize_mode(), Resize::Crop);
+    assert!(Simulation::from_json(r#"{ "resize": "stretch" }"#).is_err());
+}
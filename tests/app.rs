@@ -8,6 +8,7 @@ wasm_bindgen_test_configure!(run_in_browser);
 
 // This runs a unit test in native Rust, so it can only use Rust APIs.
 #[test]
+#[allow(clippy::eq_op)] // template placeholder
 fn rust_test() {
     assert_eq!(1, 1);
 }
@@ -15,6 +16,7 @@ fn rust_test() {
 
 // This runs a unit test in the browser, so it can use browser APIs.
 #[wasm_bindgen_test]
+#[allow(clippy::eq_op)] // template placeholder
 fn web_test() {
     assert_eq!(1, 1);
 }
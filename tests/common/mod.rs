@@ -0,0 +1,13 @@
+use rust_webpack_template::params::SimParams;
+use rust_webpack_template::sim::Dish;
+
+/// A small two-species dish a few updates in, with trail to draw and save.
+pub fn started() -> Dish {
+    let mut params = SimParams { size_w: 48, size_h: 20, ..SimParams::with_species(2) };
+    params.set("num_agents", 50.).unwrap();
+    let mut dish = Dish::new(params, 1).unwrap();
+    for i in 0..10 {
+        dish.update(i);
+    }
+    dish
+}
@@ -0,0 +1,56 @@
+use rust_webpack_template::export::{ read_png, write_png, write_ppm };
+use rust_webpack_template::vec2d::Vec2d;
+
+fn trail() -> Vec2d<u8> {
+    let mut v = Vec2d::new(3, 2, 0u8);
+    v[(0, 1)] = 10;
+    v[(1, 2)] = 255;
+    v
+}
+
+#[test]
+fn ppm_layout() {
+    let mut buf = Vec::new();
+    write_ppm(&mut buf, &trail()).unwrap();
+    let header = b"P6\n3 2\n255\n";
+    assert_eq!(&buf[..header.len()], header);
+    assert_eq!(&buf[header.len()..], &[0, 0, 0, 10, 10, 10, 0, 0, 0,
+                                       0, 0, 0, 0, 0, 0, 255, 255, 255]);
+}
+
+#[test]
+fn png_round_trip() {
+    let mut buf = Vec::new();
+    write_png(&mut buf, &trail()).unwrap();
+    let mut reader = png::Decoder::new(&buf[..]).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (3, 2));
+    assert_eq!(&pixels[..info.buffer_size()], trail().as_slice());
+}
+
+#[test]
+fn png_read_back_as_gray() {
+    let mut buf = Vec::new();
+    write_png(&mut buf, &trail()).unwrap();
+    assert_eq!(read_png(&buf[..]).unwrap(), trail());
+
+    let mut rgba = Vec::new();
+    let mut enc = png::Encoder::new(&mut rgba, 2, 1);
+    enc.set_color(png::ColorType::Rgba);
+    enc.set_depth(png::BitDepth::Eight);
+    enc.write_header().unwrap().write_image_data(&[30, 60, 90, 255, 255, 255, 255, 0]).unwrap();
+    let gray = read_png(&rgba[..]).unwrap();
+    assert_eq!((gray.size_w, gray.size_h), (2, 1));
+    assert_eq!(gray.as_slice(), &[60, 255]);
+}
+
+#[test]
+fn png_reports_a_failed_last_write() {
+    let mut buf = Vec::new();
+    write_png(&mut buf, &trail()).unwrap();
+    assert!(buf.ends_with(b"IEND\xae\x42\x60\x82"));
+    // room for everything but the IEND chunk
+    let mut full = &mut [0; 1024][..buf.len() - 12];
+    assert!(write_png(&mut full, &trail()).is_err());
+}
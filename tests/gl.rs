@@ -0,0 +1,157 @@
+use std::cell::{ Cell, RefCell };
+
+use web_sys::WebGlRenderingContext as GLC;
+
+use rust_webpack_template::gl::{ Gl, GlRenderer, FRAGMENT_SHADER, VERTEX_SHADER };
+use rust_webpack_template::palette::{ Colormap, Intensity, Palette };
+use rust_webpack_template::render::Frame;
+
+mod common;
+use common::started;
+
+/// Records every call as a line of text; objects are numbered in creation order.
+#[derive(Default)]
+struct MockGl {
+    calls: RefCell<Vec<String>>,
+    uploads: RefCell<Vec<Vec<u8>>>,
+    next: Cell<u32>,
+    fail_compile: bool,
+}
+impl MockGl {
+    fn log(&self, call: String) {
+        self.calls.borrow_mut().push(call);
+    }
+    fn create(&self, what: &str) -> Option<u32> {
+        self.next.set(self.next.get() + 1);
+        self.log(format!("create_{} {}", what, self.next.get()));
+        Some(self.next.get())
+    }
+    fn take_calls(&self) -> Vec<String> {
+        self.calls.borrow_mut().drain(..).collect()
+    }
+}
+impl Gl for MockGl {
+    type Shader = u32;
+    type Program = u32;
+    type Buffer = u32;
+    type Texture = u32;
+    type Location = String;
+
+    fn create_shader(&self, _kind: u32) -> Option<u32> { self.create("shader") }
+    fn shader_source(&self, shader: &u32, _source: &str) { self.log(format!("shader_source {}", shader)) }
+    fn compile_shader(&self, shader: &u32) { self.log(format!("compile_shader {}", shader)) }
+    fn shader_compiled(&self, _shader: &u32) -> bool { !self.fail_compile }
+    fn shader_info_log(&self, _shader: &u32) -> Option<String> { Some(String::from("syntax error")) }
+
+    fn create_program(&self) -> Option<u32> { self.create("program") }
+    fn attach_shader(&self, program: &u32, shader: &u32) { self.log(format!("attach_shader {} {}", program, shader)) }
+    fn link_program(&self, program: &u32) { self.log(format!("link_program {}", program)) }
+    fn program_linked(&self, _program: &u32) -> bool { true }
+    fn program_info_log(&self, _program: &u32) -> Option<String> { None }
+    fn use_program(&self, program: Option<&u32>) { self.log(format!("use_program {:?}", program)) }
+    fn get_attrib_location(&self, _program: &u32, _name: &str) -> i32 { 0 }
+    fn get_uniform_location(&self, _program: &u32, name: &str) -> Option<String> { Some(name.to_string()) }
+    fn uniform1i(&self, location: Option<&String>, x: i32) { self.log(format!("uniform1i {:?} {}", location, x)) }
+
+    fn create_buffer(&self) -> Option<u32> { self.create("buffer") }
+    fn bind_buffer(&self, _target: u32, buffer: Option<&u32>) { self.log(format!("bind_buffer {:?}", buffer)) }
+    fn buffer_data_f32(&self, _target: u32, data: &[f32], _usage: u32) { self.log(format!("buffer_data {:?}", data)) }
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, _kind: u32, _normalized: bool, _stride: i32, _offset: i32) {
+        self.log(format!("vertex_attrib_pointer {} {}", index, size))
+    }
+    fn enable_vertex_attrib_array(&self, index: u32) { self.log(format!("enable_vertex_attrib_array {}", index)) }
+
+    fn create_texture(&self) -> Option<u32> { self.create("texture") }
+    fn active_texture(&self, unit: u32) { self.log(format!("active_texture {}", unit - GLC::TEXTURE0)) }
+    fn bind_texture(&self, _target: u32, texture: Option<&u32>) { self.log(format!("bind_texture {:?}", texture)) }
+    fn tex_parameteri(&self, _target: u32, pname: u32, param: i32) { self.log(format!("tex_parameteri {} {}", pname, param)) }
+    fn pixel_storei(&self, pname: u32, param: i32) { self.log(format!("pixel_storei {} {}", pname, param)) }
+    fn tex_image_2d(&self, _target: u32, _level: i32, internal_format: i32, width: i32, height: i32,
+                    format: u32, _kind: u32, pixels: &[u8]) -> Result<(), String> {
+        assert_eq!(internal_format as u32, format);
+        let bytes_per_texel = if format == GLC::LUMINANCE { 1 } else { 4 };
+        assert_eq!(pixels.len(), (width * height * bytes_per_texel) as usize);
+        self.log(format!("tex_image_2d {} {}x{}", if format == GLC::LUMINANCE { "LUMINANCE" } else { "RGBA" }, width, height));
+        self.uploads.borrow_mut().push(pixels.to_vec());
+        Ok(())
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32) { self.log(format!("viewport {} {} {} {}", x, y, width, height)) }
+    fn clear_color(&self, _r: f32, _g: f32, _b: f32, _a: f32) {}
+    fn clear(&self, _mask: u32) { self.log(String::from("clear")) }
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) {
+        assert_eq!(mode, GLC::TRIANGLE_STRIP);
+        self.log(format!("draw_arrays {} {}", first, count))
+    }
+}
+
+#[test]
+fn setup_happens_once() {
+    let mut renderer = GlRenderer::new(MockGl::default()).unwrap();
+    let setup = renderer.context().take_calls();
+    assert_eq!(setup.iter().filter(|c| c.starts_with("compile_shader")).count(), 2);
+    assert_eq!(setup.iter().filter(|c| c.starts_with("link_program")).count(), 1);
+    assert!(setup.contains(&String::from("buffer_data [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0]")));
+    assert!(setup.contains(&String::from("uniform1i Some(\"u_trail\") 0")));
+    assert!(setup.contains(&String::from("uniform1i Some(\"u_colormap\") 1")));
+    assert!(setup.contains(&String::from("tex_image_2d RGBA 256x1")));
+    assert_eq!(renderer.context().uploads.borrow()[0], Palette::default().lut().as_flattened());
+
+    let dish = started();
+    for _ in 0..3 {
+        renderer.draw(&dish, 60, 20).unwrap();
+        let frame = renderer.context().take_calls();
+        assert!(frame.iter().all(|c| !c.starts_with("create_") && !c.starts_with("compile")), "{:?}", frame);
+    }
+}
+
+#[test]
+fn draw_uploads_trail_and_draws_quad() {
+    let mut renderer = GlRenderer::new(MockGl::default()).unwrap();
+    renderer.context().take_calls();
+    let dish = started();
+    renderer.draw(&dish, 96, 40).unwrap();
+    let calls = renderer.context().take_calls();
+    let at = |call: &str| calls.iter().position(|c| c == call).unwrap_or_else(|| panic!("no `{}` in {:?}", call, calls));
+    // the trail goes to unit 0 with byte-aligned rows, before the quad is drawn
+    assert!(at("active_texture 0") < at("tex_image_2d LUMINANCE 48x20"));
+    assert!(at(&format!("pixel_storei {} 1", GLC::UNPACK_ALIGNMENT)) < at("tex_image_2d LUMINANCE 48x20"));
+    assert!(at("tex_image_2d LUMINANCE 48x20") < at("draw_arrays 0 4"));
+    assert!(at("viewport 0 0 96 40") < at("draw_arrays 0 4"));
+    assert_eq!(renderer.context().uploads.borrow().last().unwrap(), dish.total_trail().as_slice());
+}
+
+#[test]
+fn shaders_match_renderer() {
+    for name in ["a_position", "v_uv"] {
+        assert!(VERTEX_SHADER.contains(name));
+    }
+    for name in ["u_trail", "u_colormap", "v_uv"] {
+        assert!(FRAGMENT_SHADER.contains(name));
+    }
+    let err = GlRenderer::new(MockGl { fail_compile: true, ..MockGl::default() }).err();
+    assert_eq!(err.as_deref(), Some("syntax error"));
+    let renderer = GlRenderer::new(MockGl::default()).unwrap();
+    assert!(renderer.set_colormap(&[0; 12]).is_err());
+}
+
+#[test]
+fn palettes_reach_the_gpu() {
+    let mut renderer = GlRenderer::new(MockGl::default()).unwrap();
+    let magma = Palette { colormap: Colormap::Magma, ..Palette::default() };
+    renderer.set_palette(magma.clone()).unwrap();
+    assert_eq!(renderer.context().uploads.borrow().last().unwrap(), magma.lut().as_flattened());
+
+    // species colors are blended on the CPU and drawn as they are
+    let blended = Palette { species_colors: vec![[255, 0, 0], [0, 255, 255]], ..Palette::default() };
+    renderer.set_palette(blended.clone()).unwrap();
+    renderer.context().take_calls();
+    let dish = started();
+    renderer.draw(&dish, 48, 20).unwrap();
+    let calls = renderer.context().take_calls();
+    assert!(calls.contains(&String::from("uniform1i Some(\"u_direct\") 1")), "{:?}", calls);
+    assert!(calls.contains(&String::from("tex_image_2d RGBA 48x20")), "{:?}", calls);
+    let mut frame = Frame::with_palette(blended);
+    assert_eq!(renderer.context().uploads.borrow().last().unwrap(), frame.draw(&dish));
+    assert!(renderer.set_palette(Palette { intensity: Intensity::Gamma(-1.), ..Palette::default() }).is_err());
+}
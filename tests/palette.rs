@@ -0,0 +1,62 @@
+use rust_webpack_template::palette::{ parse_color, Colormap, Intensity, Palette };
+use rust_webpack_template::params::SimParams;
+use rust_webpack_template::render::Frame;
+use rust_webpack_template::sim::Dish;
+
+#[test]
+fn colormaps() {
+    assert_eq!(Colormap::Gray.color(0.), [0, 0, 0]);
+    assert_eq!(Colormap::Gray.color(1.), [255, 255, 255]);
+    assert_eq!(Colormap::Viridis.color(0.), [0x44, 0x01, 0x54]);
+    assert_eq!(Colormap::Magma.color(1.), [0xfc, 0xfd, 0xbf]);
+    assert_eq!(Colormap::parse("inferno"), Ok(Colormap::Inferno));
+
+    let gradient = Colormap::parse("#000000, #ff0000,0000ff").unwrap();
+    assert_eq!(gradient, Colormap::Gradient(vec![(0., [0, 0, 0]), (0.5, [255, 0, 0]), (1., [0, 0, 255])]));
+    assert_eq!(gradient.color(0.25), [128, 0, 0]);
+    assert_eq!(gradient.color(0.75), [128, 0, 128]);
+    assert_eq!(gradient.color(1.), [0, 0, 255]);
+    assert!(Colormap::parse("plasma").is_err());
+    assert!(Colormap::Gradient(vec![(0.5, [0; 3]), (0.2, [0; 3])]).validate().is_err());
+    assert!(parse_color("#12345").is_err());
+    assert_eq!(parse_color("#0a0B0c"), Ok([10, 11, 12]));
+}
+
+#[test]
+fn intensity_brightens_faint_trail() {
+    for intensity in [Intensity::Linear, Intensity::Gamma(0.5), Intensity::Log] {
+        assert_eq!(intensity.apply(0.), 0.);
+        assert!((intensity.apply(1.) - 1.).abs() < 1e-12);
+    }
+    assert!(Intensity::Gamma(0.5).apply(0.1) > 0.1);
+    assert!(Intensity::Log.apply(0.1) > Intensity::Gamma(0.5).apply(0.1));
+    let palette = Palette { intensity: Intensity::Gamma(0.), ..Palette::default() };
+    assert!(palette.validate().is_err());
+
+    let lut = Palette { colormap: Colormap::Viridis, intensity: Intensity::Log, ..Palette::default() }.lut();
+    assert_eq!(lut.len(), 256);
+    assert_eq!(lut[0], [0x44, 0x01, 0x54, 255]);
+    assert_eq!(lut[255], [0xfd, 0xe7, 0x25, 255]);
+}
+
+#[test]
+fn species_colors_blend() {
+    let mut params = SimParams { size_w: 8, size_h: 8, ..SimParams::with_species(3) };
+    params.set("num_agents", 1.).unwrap();
+    let mut dish = Dish::new(params, 0).unwrap();
+    for channel in &mut dish.data {
+        channel.for_each(|v| *v = 0);
+    }
+    dish.data[0][(0, 0)] = 255;
+    dish.data[1][(0, 1)] = 255;
+    dish.data[0][(0, 2)] = 255;
+    dish.data[1][(0, 2)] = 255;
+    dish.data[2][(0, 3)] = 255;
+
+    // two colors for three species: the third reuses the first
+    let palette = Palette { species_colors: vec![[255, 0, 0], [0, 0, 255]], ..Palette::default() };
+    let mut frame = Frame::with_palette(palette);
+    let pixels = frame.draw(&dish);
+    let px: Vec<_> = pixels.chunks(4).take(5).collect();
+    assert_eq!(px, [[255, 0, 0, 255], [0, 0, 255, 255], [255, 0, 255, 255], [255, 0, 0, 255], [0, 0, 0, 255]]);
+}
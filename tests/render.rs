@@ -0,0 +1,45 @@
+use rust_webpack_template::render::Frame;
+use rust_webpack_template::vec2d::Resize;
+
+mod common;
+use common::started;
+
+#[test]
+fn frame_matches_total_trail() {
+    let dish = started();
+    let mut frame = Frame::new();
+    let pixels = frame.draw(&dish).to_vec();
+    let total = dish.total_trail();
+    assert_eq!(pixels.len(), 48 * 20 * 4);
+    for (pixel, &v) in pixels.chunks(4).zip(total.as_slice()) {
+        assert_eq!(pixel, [v, v, v, 255]);
+    }
+}
+
+#[test]
+fn frame_is_row_major() {
+    let mut dish = started();
+    for channel in &mut dish.data {
+        channel.for_each(|v| *v = 0);
+    }
+    // a wide dish: (y, x) = (3, 40) is only addressable one way round
+    dish.data[0][(3, 40)] = 200;
+    let mut frame = Frame::new();
+    let pixels = frame.draw(&dish);
+    let lit: Vec<_> = pixels.chunks(4).enumerate().filter(|(_, p)| p[0] > 0).map(|(i, _)| i).collect();
+    assert_eq!(lit, [3 * 48 + 40]);
+}
+
+#[test]
+fn frame_buffer_is_reused() {
+    let mut dish = started();
+    let mut frame = Frame::new();
+    let first = frame.draw(&dish).as_ptr();
+    dish.update(10);
+    assert_eq!(frame.draw(&dish).as_ptr(), first);
+    assert_eq!(frame.pixels().len(), 48 * 20 * 4);
+
+    dish.resize(24, 10, Resize::Nearest).unwrap();
+    frame.draw(&dish);
+    assert_eq!((frame.size_w, frame.size_h, frame.pixels().len()), (24, 10, 24 * 10 * 4));
+}
@@ -0,0 +1,1209 @@
+use rust_webpack_template::kernel::Kernel;
+use rand::rngs::mock::StepRng;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+use rust_webpack_template::params::{ Decay, DepositMode, SensorArray, SensorShape, SimParams, SpeciesParams };
+use rust_webpack_template::steering::{ Steering, SteeringMode };
+use rust_webpack_template::sim::{ Dish, World };
+use rust_webpack_template::vec2d::{ Boundary, Vec2d };
+
+fn small(size_w: usize, size_h: usize) -> SimParams {
+    SimParams { size_w, size_h, species: vec![SpeciesParams { num_agents: 100, ..SpeciesParams::default() }],
+                ..SimParams::default() }
+}
+
+#[test]
+fn vec2d_index_wraps() {
+    let mut v = Vec2d::new(4, 3, 0u8);
+    v[(-1, -1)] = 7;
+    assert_eq!(v[(2, 3)], 7);
+    assert_eq!(v[(5, 7)], 7);
+    assert_eq!(v.as_slice().iter().filter(|&&x| x > 0).count(), 1);
+}
+
+#[test]
+fn update_keeps_agents_in_world() {
+    let mut dish = Dish::new(small(64, 32), 0).unwrap();
+    for i in 0..20 {
+        dish.update(i);
+    }
+    for agent in &dish.agents {
+        assert!((0. ..64.).contains(&agent.pos_x));
+        assert!((0. ..32.).contains(&agent.pos_y));
+    }
+    assert!(dish.data[0].as_slice().iter().any(|&x| x > 0));
+}
+
+#[test]
+fn sparse_diffuse_matches_dense() {
+    let mut dish = Dish::new(small(64, 32), 0).unwrap();
+    for i in 0..10 {
+        dish.update(i);
+    }
+    let mut dense = dish.clone();
+    dish.diffuse();
+    dense.diffuse_nsquared();
+    assert_eq!(dish.data, dense.data);
+}
+
+#[test]
+fn decay_scales_trail() {
+    let mut dish = Dish::new(small(16, 16), 0).unwrap();
+    dish.data[0][(5, 5)] = 200;
+    dish.data[0][(6, 5)] = 1;
+    dish.decay_nsquared();
+    assert_eq!(dish.data[0][(5, 5)], 194);
+    assert_eq!(dish.data[0][(6, 5)], 0);
+}
+
+#[test]
+fn decay_uses_params() {
+    let mut dish = Dish::new(SimParams { decay: Decay::Multiplicative { factor: 0.5 }, ..small(16, 16) }, 0).unwrap();
+    dish.data[0][(5, 5)] = 200;
+    dish.decay_nsquared();
+    assert_eq!(dish.data[0][(5, 5)], 100);
+}
+
+#[test]
+fn wider_diffuse_radius_spreads_further() {
+    let mut dish = Dish::new(SimParams { kernel: Kernel::Box { radius: 2 }, ..small(16, 16) }, 0).unwrap();
+    dish.data[0][(8, 8)] = 250;
+    dish.diffuse_nsquared();
+    assert_eq!(dish.data[0][(8, 10)], 10);
+    assert_eq!(dish.data[0][(8, 11)], 0);
+}
+
+#[test]
+fn params_validation() {
+    assert!(SimParams::default().validate().is_ok());
+    assert!(SimParams { decay: Decay::Multiplicative { factor: 1.5 }, ..SimParams::default() }.validate().is_err());
+    assert!(SimParams { size_w: 0, ..SimParams::default() }.validate().is_err());
+    assert!(SimParams { kernel: Kernel::Box { radius: -1 }, ..SimParams::default() }.validate().is_err());
+    assert!(SimParams { species: vec![], ..SimParams::default() }.validate().is_err());
+    assert!(SimParams { size_h: 1 << 20, ..SimParams::default() }.validate().is_err());
+    let mut wide = SimParams { size_w: 64, size_h: 64, ..SimParams::default() };
+    wide.set("sensor_radius", 31.).unwrap();
+    assert!(wide.validate().is_ok());
+    wide.set("sensor_radius", 300.).unwrap();
+    assert!(wide.validate().is_err());
+    wide.set_str("sensor_shape", "point").unwrap();
+    assert!(wide.validate().is_ok());
+    let mut bad = SimParams::with_species(2);
+    bad.set("num_agents", usize::MAX as f64).unwrap();
+    assert!(bad.validate().is_err());
+    let mut bad = SimParams::default();
+    bad.species[0].velocity = f64::NAN;
+    assert!(bad.validate().is_err());
+    let mut bad = SimParams::with_species(2);
+    bad.species[1].weights.pop();
+    assert!(bad.validate().is_err());
+    let mut bad = SimParams::default();
+    bad.set("sensor_angle", 4.).unwrap();
+    assert!(Dish::new(bad, 0).is_err());
+
+    let mut dish = Dish::new(small(16, 16), 0).unwrap();
+    assert!(dish.set_params(small(32, 16)).is_err());
+    assert!(dish.set_params(SimParams { size_w: 16, size_h: 16, ..SimParams::with_species(2) }).is_err());
+    let mut slow = small(16, 16);
+    slow.species[0].velocity = 1.;
+    assert!(dish.set_params(slow).is_ok());
+    assert!(dish.agents.iter().all(|a| a.vel == 1.));
+}
+
+#[test]
+fn set_params_changes_agent_counts() {
+    let mut params = SimParams { size_w: 32, size_h: 32, ..SimParams::with_species(2) };
+    params.set("num_agents", 40.).unwrap();
+    let mut dish = Dish::new(params.clone(), 0).unwrap();
+    let count = |dish: &Dish, s: usize| dish.agents.iter().filter(|a| a.species == s).count();
+    let first = dish.agents[0].clone();
+
+    params.species[0].num_agents = 100;
+    params.species[1].num_agents = 10;
+    dish.set_params(params.clone()).unwrap();
+    assert_eq!((count(&dish, 0), count(&dish, 1)), (100, 10));
+    assert_eq!(dish.agents[0], first);
+    assert!(dish.agents.iter().all(|a| a.pos_x >= 0. && a.pos_x < 32. && a.pos_y >= 0. && a.pos_y < 32.));
+
+    // other changes leave agents alone, even ones the brush added
+    dish.agents.push(first);
+    params.species[0].sensor_angle = 0.5;
+    dish.set_params(params).unwrap();
+    assert_eq!((count(&dish, 0), count(&dish, 1)), (101, 10));
+}
+
+#[test]
+fn same_seed_is_reproducible() {
+    let run = |seed| {
+        let mut dish = Dish::new(small(64, 32), seed).unwrap();
+        for i in 0..30 {
+            dish.update(i);
+        }
+        dish
+    };
+    let (a, b, c) = (run(7), run(7), run(8));
+    assert_eq!(a.data, b.data);
+    assert!(a.agents.iter().zip(&b.agents).all(|(x, y)| x.pos_x.to_bits() == y.pos_x.to_bits()
+                                                       && x.pos_y.to_bits() == y.pos_y.to_bits()
+                                                       && x.heading.to_bits() == y.heading.to_bits()));
+    assert_ne!(a.data, c.data);
+}
+
+#[test]
+fn params_set_by_name() {
+    let mut params = SimParams::default();
+    params.set("sensor_distance", 12.).unwrap();
+    params.set("num_agents", 50.).unwrap();
+    assert_eq!(params.species[0].sensor_distance, 12.);
+    assert_eq!(params.species[0].num_agents, 50);
+    assert!(params.set("nope", 1.).is_err());
+
+    params.set("species", 3.).unwrap();
+    assert_eq!(params.species.len(), 3);
+    assert_eq!(params.species[2].sensor_distance, 12.);
+    assert_eq!(params.species[2].weights, vec![-1., -1., 1.]);
+    assert!(params.set("species", 1e5).is_err());
+    assert_eq!(params.species.len(), 3);
+    params.set("species.1.velocity", 4.).unwrap();
+    params.set("species.1.weight.0", 0.5).unwrap();
+    assert_eq!((params.species[0].velocity, params.species[1].velocity), (2., 4.));
+    assert_eq!(params.species[1].weights[0], 0.5);
+    assert!(params.set("species.3.velocity", 1.).is_err());
+    assert!(params.set("species.0.weight.3", 1.).is_err());
+    assert!(params.validate().is_ok());
+}
+
+#[test]
+fn species_deposit_into_own_channel() {
+    let mut params = SimParams { size_w: 64, size_h: 32, ..SimParams::with_species(2) };
+    params.species[0].num_agents = 30;
+    params.species[1].num_agents = 0;
+    let mut dish = Dish::new(params, 1).unwrap();
+    assert_eq!(dish.data.len(), 2);
+    for i in 0..5 {
+        dish.update(i);
+    }
+    assert!(dish.data[0].as_slice().iter().any(|&v| v > 0));
+    assert!(dish.data[1].as_slice().iter().all(|&v| v == 0));
+    assert_eq!(dish.total_trail(), dish.data[0]);
+}
+
+#[test]
+fn species_repelled_by_other_trails() {
+    use rust_webpack_template::sim::Agent;
+
+    let params = SimParams::with_species(2);
+    let mut data = vec![Vec2d::new(64, 64, 0u8); 2];
+    // species 1 trail straight ahead of an agent at (32, 32) heading diagonally, nothing else around
+    let heading = std::f64::consts::FRAC_PI_4;
+    for y in 34..42 {
+        for x in 34..42 {
+            data[1][(y, x)] = 200;
+        }
+    }
+    let mut agent = Agent { pos_x: 32., pos_y: 32., vel: 0., heading, species: 0,
+                            prev: 0, sensed: vec![] };
+    agent.update(&data, &params.species[0], &World::default(), &mut Pcg32::seed_from_u64(0));
+    assert!(agent.sensed[1] < 0.);
+    assert!(agent.heading != heading);
+
+    let mut friend = Agent { species: 1, heading, ..agent.clone() };
+    friend.update(&data, &params.species[1], &World::default(), &mut Pcg32::seed_from_u64(0));
+    assert!(friend.sensed[1] > 0.);
+    assert_eq!(friend.heading, heading);
+}
+
+#[test]
+fn bilinear_deposit_splits_by_position() {
+    use rust_webpack_template::sim::Agent;
+
+    let agent = Agent { pos_x: 10.25, pos_y: 4.5, vel: 0., heading: 0., species: 0,
+                        prev: 0, sensed: vec![] };
+    assert_eq!(agent.deposit(DepositMode::Nearest, 100.)[0], (5, 10, 100.));
+    let splat = agent.deposit(DepositMode::Bilinear, 100.);
+    assert_eq!(splat, [(4, 10, 37.5), (4, 11, 12.5), (5, 10, 37.5), (5, 11, 12.5)]);
+    assert_eq!(splat.iter().map(|s| s.2).sum::<f32>(), 100.);
+}
+
+#[test]
+fn bilinear_deposit_accumulates_before_rounding() {
+    let mut params = small(32, 32);
+    params.deposit_mode = DepositMode::Bilinear;
+    params.decay = Decay::Multiplicative { factor: 1. };
+    params.kernel = Kernel::Box { radius: 0 };
+    params.species[0].num_agents = 0;
+    params.species[0].deposit_amount = 1.;
+    let mut dish = Dish::new(params, 0).unwrap();
+    // four agents each leaving a quarter unit in the same cell: rounding each alone would drop it
+    for _ in 0..4 {
+        dish.agents.push(rust_webpack_template::sim::Agent {
+            pos_x: 10.5, pos_y: 10.5, vel: 0., heading: 0., species: 0,
+            prev: 0, sensed: vec![],
+        });
+    }
+    dish.update(0);
+    assert_eq!(dish.data[0][(10, 10)], 1);
+    assert_eq!(dish.data[0][(11, 11)], 1);
+}
+
+#[test]
+fn deposit_mode_by_name() {
+    let mut params = SimParams::default();
+    params.set_str("deposit_mode", "bilinear").unwrap();
+    params.set_str("deposit_amount", "40").unwrap();
+    assert_eq!(params.deposit_mode, DepositMode::Bilinear);
+    assert_eq!(params.species[0].deposit_amount, 40.);
+    assert!(params.set_str("deposit_mode", "smeared").is_err());
+}
+
+#[test]
+fn float_trail_keeps_faint_values() {
+    let mut params = small(16, 16);
+    params.species[0].num_agents = 0;
+    let mut bytes = Dish::new(params.clone(), 0).unwrap();
+    let mut floats = Dish::<f32>::with_trail(params, 0).unwrap();
+    bytes.data[0][(8, 8)] = 20;
+    floats.data[0][(8, 8)] = 20.;
+    bytes.diffuse_nsquared();
+    floats.diffuse_nsquared();
+    // 20 / 9 truncates to 2 in u8 but stays exact in f32
+    assert_eq!(bytes.data[0][(8, 9)], 2);
+    assert!((floats.data[0][(8, 9)] - 20. / 9.).abs() < 1e-6);
+    for _ in 0..100 {
+        bytes.decay_nsquared();
+        floats.decay_nsquared();
+    }
+    assert_eq!(bytes.data[0][(8, 9)], 0);
+    assert!(floats.data[0][(8, 9)] > 0.);
+}
+
+#[test]
+fn wide_trails_dont_clip_at_255() {
+    let mut params = small(16, 16);
+    params.species[0].num_agents = 0;
+    params.kernel = Kernel::Box { radius: 0 };
+    params.decay = Decay::Multiplicative { factor: 1. };
+    let mut dish = Dish::<u16>::with_trail(params, 0).unwrap();
+    for _ in 0..3 {
+        dish.agents.push(rust_webpack_template::sim::Agent {
+            pos_x: 4., pos_y: 4., vel: 0., heading: 0., species: 0,
+            prev: 0, sensed: vec![],
+        });
+    }
+    dish.update(0);
+    assert_eq!(dish.data[0][(4, 4)], 765);
+    assert_eq!(dish.total_trail()[(4, 4)], 255);
+}
+
+#[test]
+fn float_sparse_diffuse_matches_dense() {
+    let mut dish = Dish::<f32>::with_trail(small(64, 32), 2).unwrap();
+    for i in 0..10 {
+        dish.update(i);
+    }
+    let mut dense = dish.clone();
+    dish.diffuse();
+    dense.diffuse_nsquared();
+    assert_eq!(dish.data, dense.data);
+}
+
+#[test]
+fn smooth_kernels_sparse_matches_dense() {
+    for kernel in [Kernel::Gaussian { sigma: 1.5 },
+                   Kernel::Anisotropic { sigma_along: 2., sigma_across: 0.5, angle: 0.7 }] {
+        let mut dish = Dish::<f32>::with_trail(SimParams { kernel, ..small(64, 32) }, 3).unwrap();
+        for i in 0..10 {
+            dish.update(i);
+        }
+        let mut dense = dish.clone();
+        dish.diffuse();
+        dense.diffuse_nsquared();
+        assert_eq!(dish.data, dense.data, "{:?}", kernel);
+    }
+}
+
+#[test]
+fn separable_gaussian_matches_full_stencil() {
+    use rust_webpack_template::kernel::DiffusionKernel;
+
+    // the same weights, but only as a 2D stencil
+    struct Full(Kernel);
+    impl DiffusionKernel for Full {
+        fn radius(&self) -> i32 { self.0.radius() }
+        fn weight(&self, dy: i32, dx: i32) -> f64 { self.0.weight(dy, dx) }
+    }
+    let kernel = Kernel::Gaussian { sigma: 2.5 };
+    assert_eq!(kernel.separable().map(|line| line.len()), Some(17));
+    for boundary in [Boundary::Periodic, Boundary::Reflective, Boundary::Absorbing, Boundary::Clamped] {
+        let mut dish = Dish::<f32>::with_trail(SimParams { kernel, boundary, ..small(40, 24) }, 5).unwrap();
+        for i in 0..10 {
+            dish.update(i);
+        }
+        let mut full = dish.clone();
+        let mut dense = dish.clone();
+        dish.diffuse();
+        dense.diffuse_nsquared();
+        full.diffuse_nsquared_with(&Full(kernel));
+        assert_eq!(dish.data, dense.data, "{:?}", boundary);
+        for (a, b) in dish.data[0].as_slice().iter().zip(full.data[0].as_slice()) {
+            assert!((a - b).abs() <= 1e-4 * b.max(1.), "{:?}: {} vs {}", boundary, a, b);
+        }
+    }
+}
+
+#[test]
+fn kernel_shapes() {
+    let spread = |kernel| {
+        let mut params = SimParams { kernel, ..small(32, 32) };
+        params.species[0].num_agents = 0;
+        let mut dish = Dish::<f32>::with_trail(params, 0).unwrap();
+        dish.data[0][(16, 16)] = 100.;
+        dish.diffuse_nsquared();
+        let total: f32 = dish.data[0].as_slice().iter().sum();
+        assert!((total - 100.).abs() < 1e-3, "{:?} lost mass: {}", kernel, total);
+        dish
+    };
+    let round = spread(Kernel::Gaussian { sigma: 1. });
+    assert_eq!(round.data[0][(16, 18)], round.data[0][(18, 16)]);
+    assert!(round.data[0][(16, 17)] > round.data[0][(17, 17)]);
+    // stretched along x: spreads further sideways than up and down
+    let wide = spread(Kernel::Anisotropic { sigma_along: 2., sigma_across: 0.5, angle: 0. });
+    assert!(wide.data[0][(16, 18)] > 10. * wide.data[0][(18, 16)]);
+    let tall = spread(Kernel::Anisotropic { sigma_along: 2., sigma_across: 0.5, angle: std::f64::consts::FRAC_PI_2 });
+    assert!(tall.data[0][(18, 16)] > 10. * tall.data[0][(16, 18)]);
+}
+
+#[test]
+fn diffuse_mix_blends_with_original() {
+    let mut params = small(16, 16);
+    params.species[0].num_agents = 0;
+    params.diffuse_mix = 0.5;
+    let mut dish = Dish::<f32>::with_trail(params, 0).unwrap();
+    dish.data[0][(8, 8)] = 90.;
+    dish.diffuse_nsquared();
+    assert_eq!(dish.data[0][(8, 8)], 50.);
+    assert_eq!(dish.data[0][(8, 9)], 5.);
+    dish.params.diffuse_mix = 0.;
+    let before = dish.data.clone();
+    dish.diffuse_nsquared();
+    assert_eq!(dish.data, before);
+}
+
+#[test]
+fn kernel_params_by_name() {
+    let mut params = SimParams::default();
+    params.set("gaussian_sigma", 2.).unwrap();
+    assert_eq!(params.kernel, Kernel::Gaussian { sigma: 2. });
+    params.set("anisotropic_angle", 1.).unwrap();
+    params.set("anisotropic_along", 3.).unwrap();
+    assert_eq!(params.kernel, Kernel::Anisotropic { sigma_along: 3., sigma_across: 0.5, angle: 1. });
+    params.set("diffuse_radius", 2.).unwrap();
+    params.set("diffuse_mix", 0.8).unwrap();
+    assert_eq!((params.kernel, params.diffuse_mix), (Kernel::Box { radius: 2 }, 0.8));
+    assert!(params.validate().is_ok());
+    assert!(SimParams { kernel: Kernel::Gaussian { sigma: 0. }, ..SimParams::default() }.validate().is_err());
+    assert!(SimParams { kernel: Kernel::Gaussian { sigma: 50. }, ..SimParams::default() }.validate().is_err());
+    assert!(SimParams { diffuse_mix: 1.5, ..SimParams::default() }.validate().is_err());
+}
+
+#[test]
+fn decay_laws() {
+    let decayed = |decay, v: f32| {
+        let mut params = SimParams { decay, ..small(16, 16) };
+        params.species[0].num_agents = 0;
+        let mut dish = Dish::<f32>::with_trail(params, 0).unwrap();
+        dish.data[0][(5, 5)] = v;
+        dish.decay_nsquared();
+        dish.data[0][(5, 5)]
+    };
+    assert_eq!(decayed(Decay::Linear { amount: 3. }, 10.), 7.);
+    assert_eq!(decayed(Decay::Linear { amount: 3. }, 2.), 0.);
+    assert_eq!(decayed(Decay::Threshold { factor: 0.5, cutoff: 4. }, 10.), 5.);
+    assert_eq!(decayed(Decay::Threshold { factor: 0.5, cutoff: 4. }, 6.), 0.);
+    let mut v = 64.;
+    for _ in 0..4 {
+        v = decayed(Decay::HalfLife { steps: 2. }, v);
+    }
+    assert!((v - 16.).abs() < 1e-4);
+}
+
+#[test]
+fn sparse_decay_matches_dense() {
+    for decay in [Decay::Multiplicative { factor: 0.9 }, Decay::Linear { amount: 0.5 },
+                  Decay::HalfLife { steps: 5. }, Decay::Threshold { factor: 0.95, cutoff: 1. }] {
+        let mut dish = Dish::<f32>::with_trail(SimParams { decay, ..small(64, 32) }, 1).unwrap();
+        for i in 0..10 {
+            dish.update(i);
+        }
+        let mut dense = dish.clone();
+        dish.decay();
+        dense.decay_nsquared();
+        assert_eq!(dish.data, dense.data, "{:?}", decay);
+    }
+}
+
+#[test]
+fn decay_params_by_name() {
+    let mut params = SimParams::default();
+    params.set("decay_cutoff", 2.).unwrap();
+    assert_eq!(params.decay, Decay::Threshold { factor: 0.97, cutoff: 2. });
+    params.set("decay", 0.9).unwrap();
+    assert_eq!(params.decay, Decay::Threshold { factor: 0.9, cutoff: 2. });
+    params.set("decay_half_life", 10.).unwrap();
+    assert_eq!(params.decay, Decay::HalfLife { steps: 10. });
+    params.set("decay_linear", 1.).unwrap();
+    assert_eq!(params.decay, Decay::Linear { amount: 1. });
+    assert!(params.validate().is_ok());
+    params.set("decay_half_life", 0.).unwrap();
+    assert!(params.validate().is_err());
+}
+
+/// Forward sensor reading of an agent at (32, 32) heading along +x, so the sensor sits on (32, 40).
+fn forward_reading(sensor_shape: SensorShape, sensor_radius: f64, data: &Vec2d<u8>) -> f64 {
+    use rust_webpack_template::sim::Agent;
+
+    let params = SpeciesParams { sensor_shape, sensor_radius, ..SpeciesParams::default() };
+    let mut agent = Agent { pos_x: 32., pos_y: 32., vel: 0., heading: 0., species: 0,
+                            prev: 0, sensed: vec![] };
+    agent.update(std::slice::from_ref(data), &params, &World::default(), &mut Pcg32::seed_from_u64(0));
+    agent.sensed[1]
+}
+
+#[test]
+fn sensor_footprint_coverage() {
+    let ones = Vec2d::new(64, 64, 1u8);
+    assert_eq!(forward_reading(SensorShape::Point, 2., &ones), 1.);
+    assert_eq!(forward_reading(SensorShape::Square, 2., &ones), 25.);
+    assert_eq!(forward_reading(SensorShape::Disk, 2., &ones), 13.);
+    assert_eq!(forward_reading(SensorShape::Disk, 1.5, &ones), 9.);
+    assert_eq!(forward_reading(SensorShape::Disk, 3., &ones), 29.);
+    let gaussian = forward_reading(SensorShape::GaussianDisk, 2., &ones);
+    let expected = 1. + 4. * (-0.5f64).exp() + 4. * (-2f64).exp() + 4. * (-1f64).exp();
+    assert!((gaussian - expected).abs() < 1e-12);
+
+    // each shape sees exactly the cells it claims to, symmetrically around the sensor
+    for shape in [SensorShape::Point, SensorShape::Square, SensorShape::Disk, SensorShape::GaussianDisk] {
+        for dy in -4..=4 {
+            for dx in -4..=4 {
+                let mut data = Vec2d::new(64, 64, 0u8);
+                data[(32 + dy, 40 + dx)] = 1;
+                let seen = forward_reading(shape, 2., &data);
+                assert_eq!(seen, shape.weight(dy, dx, 2.), "{:?} at ({}, {})", shape, dy, dx);
+                assert_eq!(seen > 0., match shape {
+                    SensorShape::Point => (dy, dx) == (0, 0),
+                    SensorShape::Square => dy.abs() <= 2 && dx.abs() <= 2,
+                    _ => dy * dy + dx * dx <= 4,
+                }, "{:?} at ({}, {})", shape, dy, dx);
+            }
+        }
+    }
+}
+
+#[test]
+fn sensor_shape_by_name() {
+    let mut params = SimParams::with_species(2);
+    params.set_str("sensor_shape", "disk").unwrap();
+    params.set_str("species.1.sensor_shape", "gaussian").unwrap();
+    assert_eq!(params.species[0].sensor_shape, SensorShape::Disk);
+    assert_eq!(params.species[1].sensor_shape, SensorShape::GaussianDisk);
+    assert!(params.set_str("sensor_shape", "hexagon").is_err());
+    assert!(params.set_str("species.2.sensor_shape", "point").is_err());
+}
+
+/// Heading change of an agent at (32, 32) heading along +x whose point sensors read `[lef, fwd, rig]`.
+fn turn(steering: SteeringMode, heading_jitter: f64, readings: [u8; 3], rng: &mut impl rand::Rng) -> f64 {
+    use rust_webpack_template::sim::Agent;
+
+    let params = SpeciesParams { sensor_shape: SensorShape::Point, steering, heading_jitter, ..SpeciesParams::default() };
+    let mut data = Vec2d::new(64, 64, 0u8);
+    // sensors 8 cells out at +60, 0 and -60 degrees; positive turns head toward `lef`
+    data[(39, 36)] = readings[0];
+    data[(32, 40)] = readings[1];
+    data[(25, 36)] = readings[2];
+    let mut agent = Agent { pos_x: 32., pos_y: 32., vel: 0., heading: 0., species: 0,
+                            prev: 0, sensed: vec![] };
+    agent.update(std::slice::from_ref(&data), &params, &World::default(), rng);
+    assert_eq!(agent.sensed, readings.map(f64::from));
+    agent.heading
+}
+
+#[test]
+fn agents_turn_toward_stronger_trail() {
+    use rust_webpack_template::sim::Agent;
+
+    // a blob ahead and to one side of an agent heading along +x; whichever side it's on,
+    // the agent should end up heading closer to it
+    for side in [-1., 1.] {
+        let mut data = Vec2d::new(64, 64, 0u8);
+        let (by, bx) = (32. + side * 8., 38.);
+        for y in -2..=2 {
+            for x in -2..=2 {
+                data[(by as i32 + y, bx as i32 + x)] = 200;
+            }
+        }
+        let params = SpeciesParams::default();
+        let mut agent = Agent { pos_x: 32., pos_y: 32., vel: 1., heading: 0., species: 0,
+                                prev: 0, sensed: vec![] };
+        let bearing = |a: &Agent| ((by - a.pos_y).atan2(bx - a.pos_x) - a.heading).abs();
+        let before = bearing(&agent);
+        agent.update(std::slice::from_ref(&data), &params, &World::default(), &mut Pcg32::seed_from_u64(0));
+        assert!(bearing(&agent) < before, "side {}", side);
+    }
+}
+
+#[test]
+fn jones_steering_rules() {
+    let t = SpeciesParams::default().turn_angle;
+    let (mut low, mut high) = (StepRng::new(0, 0), StepRng::new(u64::MAX, 0));
+    for rng in [&mut low, &mut high] {
+        assert_eq!(turn(SteeringMode::Jones, 0., [1, 5, 1], rng), 0.);
+        assert_eq!(turn(SteeringMode::Jones, 0., [5, 3, 1], rng), t);
+        assert_eq!(turn(SteeringMode::Jones, 0., [5, 5, 1], rng), t);
+        assert_eq!(turn(SteeringMode::Jones, 0., [1, 3, 5], rng), -t);
+    }
+    // forward weakest and all equal turn either way at random
+    assert_eq!(turn(SteeringMode::Jones, 0., [4, 1, 5], &mut low), t);
+    assert_eq!(turn(SteeringMode::Jones, 0., [4, 1, 5], &mut high), -t);
+    assert_eq!(turn(SteeringMode::Jones, 0., [2, 2, 2], &mut low), t);
+    assert_eq!(turn(SteeringMode::Jones, 0., [2, 2, 2], &mut high), -t);
+    // the weighted rule goes straight on ties
+    assert_eq!(turn(SteeringMode::Weighted, 0., [2, 2, 2], &mut low), 0.);
+}
+
+#[test]
+fn heading_jitter_stays_in_range() {
+    let mut rng = Pcg32::seed_from_u64(7);
+    let turns: Vec<f64> = (0..200).map(|_| turn(SteeringMode::Jones, 0.1, [1, 5, 1], &mut rng)).collect();
+    assert!(turns.iter().all(|t| t.abs() <= 0.1));
+    assert!(turns.iter().any(|&t| t > 0.05) && turns.iter().any(|&t| t < -0.05));
+
+    let mut params = SimParams::default();
+    params.set_str("steering", "jones").unwrap();
+    params.set("heading_jitter", 0.2).unwrap();
+    assert_eq!((params.species[0].steering, params.species[0].heading_jitter), (SteeringMode::Jones, 0.2));
+    assert!(params.set_str("steering", "boids").is_err());
+    params.set("heading_jitter", 4.).unwrap();
+    assert!(params.validate().is_err());
+}
+
+#[test]
+fn custom_steering_plugs_in() {
+    use rust_webpack_template::sim::Agent;
+
+    /// Turns species 0 agents by a fixed angle and leaves the rest alone.
+    struct Spin(f64);
+    impl Steering for Spin {
+        fn steer(&self, sensed: &[f64], agent: &Agent, _: &SpeciesParams, _: &mut dyn rand::RngCore) -> f64 {
+            assert!(sensed.len() == 3 && sensed.iter().all(|&v| v >= 0.));
+            if agent.species == 0 { self.0 } else { 0. }
+        }
+    }
+    let mut dish = Dish::new(SimParams::with_species(2), 0).unwrap();
+    let before: Vec<f64> = dish.agents.iter().map(|a| a.heading).collect();
+    dish.update_with(0, &Spin(0.25));
+    for (agent, heading) in dish.agents.iter().zip(before) {
+        let expected = if agent.species == 0 { heading + 0.25 } else { heading };
+        assert_eq!(agent.heading, expected);
+        assert_eq!(agent.prev, if agent.species == 0 { -1 } else { 0 });
+    }
+
+    // the built-in modes are steering strategies like any other
+    let mut a = Dish::new(small(64, 32), 3).unwrap();
+    let mut b = a.clone();
+    for i in 0..5 {
+        a.update(i);
+        b.update_with(i, &SteeringMode::Weighted);
+    }
+    assert_eq!(a.data, b.data);
+}
+
+#[test]
+fn fanned_sensor_offsets() {
+    let a = std::f64::consts::FRAC_PI_4;
+    let offsets = |sensors| {
+        let sp = SpeciesParams { sensors, sensor_angle: a, ..SpeciesParams::default() };
+        (0..sp.sensors.count()).map(|i| sp.sensor_offset(i)).collect::<Vec<_>>()
+    };
+    assert_eq!(offsets(SensorArray::Fan { count: 3 }), vec![a, 0., -a]);
+    assert_eq!(offsets(SensorArray::Fan { count: 5 }), vec![a, a / 2., 0., -a / 2., -a]);
+    assert_eq!(offsets(SensorArray::Fan { count: 1 }), vec![0.]);
+    assert_eq!(offsets(SensorArray::Custom(vec![0., 3.])), vec![0., 3.]);
+
+    let mut params = SimParams::default();
+    params.set("sensor_count", 7.).unwrap();
+    assert_eq!(params.species[0].sensors, SensorArray::Fan { count: 7 });
+    params.set_str("sensors", "0.5, 0, -0.5, 3").unwrap();
+    assert_eq!(params.species[0].sensors, SensorArray::Custom(vec![0.5, 0., -0.5, 3.]));
+    assert!(params.validate().is_ok());
+    assert!(params.set_str("sensors", "0.5,left").is_err());
+    params.set("sensor_count", 0.).unwrap();
+    assert!(params.validate().is_err());
+}
+
+#[test]
+fn sensor_arrays_steer_toward_trail() {
+    use rust_webpack_template::sim::Agent;
+    use std::f64::consts::{ FRAC_PI_2, PI };
+
+    // agent at (32, 32) heading along +x with sensors 6 cells out; returns (sensor count, heading after)
+    let step = |blob: (i32, i32), sensors, sensor_angle, steering| {
+        let mut data = Vec2d::new(64, 64, 0u8);
+        for y in -1..=1 {
+            for x in -1..=1 {
+                data[(blob.0 + y, blob.1 + x)] = 200;
+            }
+        }
+        let params = SpeciesParams { sensors, sensor_angle, steering, sensor_distance: 6.,
+                                     sensor_shape: SensorShape::Disk, ..SpeciesParams::default() };
+        let mut agent = Agent { pos_x: 32., pos_y: 32., vel: 0., heading: 0., species: 0,
+                                prev: 0, sensed: vec![] };
+        agent.update(std::slice::from_ref(&data), &params, &World::default(), &mut Pcg32::seed_from_u64(0));
+        (agent.sensed.len(), agent.heading)
+    };
+    let turn = SpeciesParams::default().turn_angle;
+    let classic = SensorArray::Fan { count: 3 };
+    let wide = SensorArray::Fan { count: 5 };
+    // square off to the +90 degree side: out of reach of a 60 degree fan, seen by a 90 degree one
+    let side = (38, 31);
+    assert_eq!(step(side, classic.clone(), PI / 3., SteeringMode::Gradient), (3, 0.));
+    assert_eq!(step(side, wide.clone(), FRAC_PI_2, SteeringMode::Gradient), (5, turn));
+    assert_eq!(step(side, wide, FRAC_PI_2, SteeringMode::Weighted), (5, turn));
+    // straight behind: only a rear sensor notices
+    let behind = (32, 26);
+    assert_eq!(step(behind, classic, PI / 3., SteeringMode::Gradient), (3, 0.));
+    let (count, heading) = step(behind, SensorArray::Custom(vec![1., 0., -1., PI]), PI / 3., SteeringMode::Gradient);
+    assert_eq!((count, heading.abs()), (4, turn));
+}
+
+#[test]
+fn boundary_lookups() {
+    let n = 8;
+    let resolve = |b: Boundary| [-2, -1, 0, 7, 8, 9].map(|i| b.resolve(i, n));
+    assert_eq!(resolve(Boundary::Periodic),   [Some(6), Some(7), Some(0), Some(7), Some(0), Some(1)]);
+    assert_eq!(resolve(Boundary::Reflective), [Some(1), Some(0), Some(0), Some(7), Some(7), Some(6)]);
+    assert_eq!(resolve(Boundary::Absorbing),  [None,    None,    Some(0), Some(7), None,    None   ]);
+    assert_eq!(resolve(Boundary::Clamped),    [Some(0), Some(0), Some(0), Some(7), Some(7), Some(7)]);
+
+    let mut v = Vec2d::new(4, 2, 0u8);
+    v[(1, 3)] = 5;
+    assert_eq!(v.get((1, -1), Boundary::Periodic), Some(&5));
+    assert_eq!(v.get((2, 4), Boundary::Clamped), Some(&5));
+    assert_eq!(v.get((1, 4), Boundary::Reflective), Some(&5));
+    assert_eq!(v.get((1, 4), Boundary::Absorbing), None);
+}
+
+#[test]
+fn agents_bounce_or_stop_at_walls() {
+    use rust_webpack_template::sim::Agent;
+
+    let data = [Vec2d::new(16, 16, 0u8)];
+    let params = SpeciesParams { sensors: SensorArray::Fan { count: 1 }, ..SpeciesParams::default() };
+    let moved = |boundary| {
+        let mut agent = Agent { pos_x: 14.5, pos_y: 8., vel: 2., heading: 0., species: 0,
+                                prev: 0, sensed: vec![] };
+        agent.update(&data, &params, &World { boundary, ..World::default() }, &mut Pcg32::seed_from_u64(0));
+        (agent.pos_x, agent.heading)
+    };
+    assert_eq!(moved(Boundary::Periodic), (0.5, 0.));
+    assert_eq!(moved(Boundary::Reflective), (13.5, std::f64::consts::PI));
+    assert_eq!(moved(Boundary::Absorbing), (13.5, std::f64::consts::PI));
+    assert_eq!(moved(Boundary::Clamped), (15., 0.));
+
+    for boundary in [Boundary::Reflective, Boundary::Absorbing, Boundary::Clamped] {
+        let mut dish = Dish::new(SimParams { boundary, ..small(24, 16) }, 5).unwrap();
+        for i in 0..100 {
+            dish.update(i);
+        }
+        assert!(dish.agents.iter().all(|a| (0. ..=23.).contains(&a.pos_x) && (0. ..=15.).contains(&a.pos_y)));
+    }
+}
+
+#[test]
+fn trail_at_the_edges() {
+    let spread = |boundary| {
+        let mut params = SimParams { boundary, ..small(16, 16) };
+        params.species[0].num_agents = 0;
+        let mut dish = Dish::<f32>::with_trail(params, 0).unwrap();
+        dish.data[0][(0, 0)] = 90.;
+        dish.diffuse_nsquared();
+        dish
+    };
+    // the torus spreads across to the far corner, walls keep all of it, open edges lose some
+    assert_eq!(spread(Boundary::Periodic).data[0][(15, 15)], 10.);
+    let walled = spread(Boundary::Reflective);
+    assert_eq!(walled.data[0][(15, 15)], 0.);
+    assert_eq!(walled.data[0].as_slice().iter().sum::<f32>(), 90.);
+    assert_eq!(spread(Boundary::Absorbing).data[0].as_slice().iter().sum::<f32>(), 40.);
+    assert_eq!(spread(Boundary::Clamped).data[0][(0, 0)], 90. * 4. / 9.);
+
+    for boundary in [Boundary::Reflective, Boundary::Absorbing, Boundary::Clamped] {
+        let mut dish = Dish::<f32>::with_trail(SimParams { boundary, ..small(24, 16) }, 5).unwrap();
+        for i in 0..20 {
+            dish.update(i);
+        }
+        let mut dense = dish.clone();
+        dish.diffuse();
+        dense.diffuse_nsquared();
+        assert_eq!(dish.data, dense.data, "{:?}", boundary);
+    }
+
+    let mut params = SimParams::default();
+    params.set_str("boundary", "reflective").unwrap();
+    assert_eq!(params.boundary, Boundary::Reflective);
+    assert!(params.set_str("boundary", "klein").is_err());
+}
+
+#[test]
+fn trail_doesnt_leak_through_walls() {
+    // a straight wall down column 8 and a diagonal one, each a single cell thick
+    let mut straight = Vec2d::new(16, 16, false);
+    let mut diagonal = Vec2d::new(16, 16, false);
+    for i in 0..16 {
+        straight[(i, 8)] = true;
+        diagonal[(i, i)] = true;
+    }
+    for (walls, source, shadow) in [(straight, (5, 4), (5, 12)), (diagonal, (10, 3), (3, 10))] {
+        for kernel in [Kernel::Box { radius: 1 }, Kernel::Box { radius: 2 }, Kernel::Gaussian { sigma: 1. }] {
+            let mut params = SimParams { boundary: Boundary::Reflective, kernel, ..small(16, 16) };
+            params.species[0].num_agents = 0;
+            params.decay = Decay::Multiplicative { factor: 1. };
+            let mut dish = Dish::<f32>::with_trail(params, 0).unwrap();
+            dish.set_walls(Some(walls.clone())).unwrap();
+            // a parked agent keeps topping up the source
+            dish.agents.push(rust_webpack_template::sim::Agent {
+                pos_x: source.1 as f64, pos_y: source.0 as f64, vel: 0., heading: 0., species: 0,
+                prev: 0, sensed: vec![],
+            });
+            for i in 0..30 {
+                dish.update(i);
+            }
+            let mut dense = dish.clone();
+            dish.diffuse();
+            dense.diffuse_nsquared();
+            assert_eq!(dish.data, dense.data, "{:?}", kernel);
+            assert!(dish.data[0][source] > 0.);
+            assert_eq!(dish.data[0][shadow], 0., "{:?}", kernel);
+            assert!(dish.data[0].as_slice().iter().zip(walls.as_slice()).all(|(&v, &w)| !w || v == 0.));
+        }
+    }
+}
+
+#[test]
+fn agents_stay_out_of_walls() {
+    // agents spawn around (12, 8); a wall down column 22 should keep them all on that side
+    let mut dish = Dish::new(SimParams { boundary: Boundary::Reflective, ..small(48, 32) }, 4).unwrap();
+    let mut walls = Vec2d::new(48, 32, false);
+    for y in 0..32 {
+        walls[(y, 22)] = true;
+    }
+    assert!(dish.set_walls(Some(Vec2d::new(8, 8, false))).is_err());
+    dish.set_walls(Some(walls)).unwrap();
+    for i in 0..300 {
+        dish.update(i);
+        assert!(dish.agents.iter().all(|a| a.pos_x.round() < 22.));
+    }
+    assert!(dish.agents.iter().any(|a| a.pos_x > 18.));
+    assert!(dish.data[0].as_slice().chunks(48).all(|row| row[22..].iter().all(|&v| v == 0)));
+
+    // walls from an image: dark pixels block
+    let mut image = Vec2d::new(4, 2, 200u8);
+    image[(1, 2)] = 10;
+    let walls = image.map(|&v| v < 128);
+    assert_eq!(walls.as_slice(), &[false, false, false, false, false, false, true, false]);
+}
+
+#[test]
+fn agents_walk_out_of_walls_drawn_over_them() {
+    use rust_webpack_template::spawn::SpawnPattern;
+
+    // a thick block right on top of the whole population
+    let params = SimParams { spawn: SpawnPattern::Point { x: 0.5, y: 0.5 }, ..small(48, 48) };
+    let mut dish = Dish::new(params, 2).unwrap();
+    let mut walls = Vec2d::new(48, 48, false);
+    for y in 18..31 {
+        for x in 18..31 {
+            walls[(y, x)] = true;
+        }
+    }
+    dish.set_walls(Some(walls.clone())).unwrap();
+    let inside = |dish: &Dish| dish.agents.iter().filter(|a| walls[(a.pos_y.round() as i32, a.pos_x.round() as i32)]).count();
+    assert_eq!(inside(&dish), 100);
+    for i in 0..100 {
+        dish.update(i);
+    }
+    assert_eq!(inside(&dish), 0);
+}
+
+#[test]
+fn sources_inject_trail() {
+    use rust_webpack_template::sim::Source;
+
+    let mut params = SimParams { boundary: Boundary::Reflective, ..small(32, 32) };
+    params.species[0].num_agents = 0;
+    params.decay = Decay::Multiplicative { factor: 1. };
+    params.diffuse_mix = 0.;
+    let mut dish = Dish::<f32>::with_trail(params, 0).unwrap();
+    dish.sources.push(Source { pos_x: 10., pos_y: 20., strength: 5., radius: 1.5, ..Source::default() });
+    dish.update(0);
+    // a radius of 1.5 covers the 3x3 square around the source
+    assert_eq!(dish.data[0][(20, 10)], 5.);
+    assert_eq!(dish.data[0][(21, 11)], 5.);
+    assert_eq!(dish.data[0][(22, 10)], 0.);
+    assert_eq!(dish.data[0].as_slice().iter().sum::<f32>(), 45.);
+
+    // a moving source lays a line and bounces off the edge
+    dish.sources[0] = Source { pos_x: 29., pos_y: 5., vel_x: 1., strength: 1., radius: 0., ..Source::default() };
+    dish.update(1);
+    dish.update(2);
+    dish.update(3);
+    assert_eq!(dish.sources[0].vel_x, -1.);
+    assert_eq!(dish.sources[0].pos_x, 30.);
+    assert_eq!([dish.data[0][(5, 29)], dish.data[0][(5, 30)], dish.data[0][(5, 31)]], [1., 1., 1.]);
+
+    dish.params.diffuse_mix = 1.;
+    dish.update(4);
+    let mut dense = dish.clone();
+    dish.diffuse();
+    dense.diffuse_nsquared();
+    assert_eq!(dish.data, dense.data);
+
+    assert!(Source { radius: -1., ..Source::default() }.validate().is_err());
+}
+
+#[test]
+fn agents_sense_nutrient() {
+    use rust_webpack_template::sim::Agent;
+
+    let mut params = SpeciesParams { sensor_shape: SensorShape::Point, ..SpeciesParams::default() };
+    let data = Vec2d::new(64, 64, 0u8);
+    let mut nutrient = Vec2d::new(64, 64, 0f32);
+    nutrient[(39, 36)] = 3.;
+    let world = World { nutrient: Some(&nutrient), ..World::default() };
+    let mut agent = Agent { pos_x: 32., pos_y: 32., vel: 0., heading: 0., species: 0,
+                            prev: 0, sensed: vec![] };
+    agent.update(std::slice::from_ref(&data), &params, &world, &mut Pcg32::seed_from_u64(0));
+    assert_eq!(agent.sensed, [3., 0., 0.]);
+    assert!(agent.heading > 0.);
+
+    let mut sim = SimParams::default();
+    sim.set("nutrient_weight", -2.).unwrap();
+    params.nutrient_weight = sim.species[0].nutrient_weight;
+    agent.heading = 0.;
+    agent.update(std::slice::from_ref(&data), &params, &world, &mut Pcg32::seed_from_u64(0));
+    assert_eq!(agent.sensed, [-6., 0., 0.]);
+    assert!(agent.heading < 0.);
+
+    let mut dish = Dish::new(small(16, 16), 0).unwrap();
+    assert!(dish.set_nutrient(Some(Vec2d::new(8, 16, 0.))).is_err());
+    dish.set_nutrient(Some(Vec2d::new(16, 16, 1.))).unwrap();
+}
+
+#[test]
+fn agents_avoid_repellent() {
+    use rust_webpack_template::sim::{ Agent, Source };
+
+    // repellent on the `lef` sensor turns the agent the other way
+    let params = SpeciesParams { sensor_shape: SensorShape::Point, ..SpeciesParams::default() };
+    let data = Vec2d::new(64, 64, 0u8);
+    let mut repellent = Vec2d::new(64, 64, 0f32);
+    repellent[(39, 36)] = 3.;
+    let world = World { repellent: Some(&repellent), ..World::default() };
+    let mut agent = Agent { pos_x: 32., pos_y: 32., vel: 0., heading: 0., species: 0,
+                            prev: 0, sensed: vec![] };
+    agent.update(std::slice::from_ref(&data), &params, &world, &mut Pcg32::seed_from_u64(0));
+    assert_eq!(agent.sensed, [-3., 0., 0.]);
+    assert!(agent.heading < 0.);
+
+    // a hazard source fills in a field that spreads and fades like the trail
+    let mut params = SimParams { boundary: Boundary::Reflective, ..small(32, 32) };
+    params.species[0].num_agents = 0;
+    params.decay = Decay::Multiplicative { factor: 0.5 };
+    let mut dish = Dish::new(params, 0).unwrap();
+    dish.sources.push(Source { pos_x: 16., pos_y: 16., strength: 0., repellent: 9., radius: 0., ..Source::default() });
+    dish.update(0);
+    let repellent = dish.repellent.as_ref().unwrap();
+    assert_eq!(repellent[(16, 16)], 0.5);
+    assert_eq!(repellent[(17, 15)], 0.5);
+    assert_eq!(repellent[(18, 16)], 0.);
+    assert!(dish.data[0].as_slice().iter().all(|&v| v == 0));
+    dish.sources.clear();
+    for i in 1..40 {
+        dish.update(i);
+    }
+    assert!(dish.repellent.as_ref().unwrap().as_slice().iter().all(|&v| v < 1e-6));
+
+    assert!(dish.set_repellent(Some(Vec2d::new(16, 32, 0.))).is_err());
+    let mut walls = Vec2d::new(32, 32, false);
+    walls[(0, 0)] = true;
+    dish.set_walls(Some(walls)).unwrap();
+    dish.set_repellent(Some(Vec2d::new(32, 32, 1.))).unwrap();
+    assert_eq!(dish.repellent.as_ref().unwrap()[(0, 0)], 0.);
+
+    let mut params = SimParams::default();
+    params.set("species.0.repellent_weight", 2.5).unwrap();
+    assert_eq!(params.species[0].repellent_weight, 2.5);
+}
+
+#[test]
+fn spawn_patterns() {
+    use rust_webpack_template::spawn::{ Facing, SpawnPattern };
+    use std::f64::consts::PI;
+
+    let spawn = |spawn: SpawnPattern| {
+        let dish = Dish::new(SimParams { spawn, ..small(100, 50) }, 3).unwrap();
+        assert_eq!(dish.agents.len(), 100);
+        assert!(dish.agents.iter().all(|a| (0. ..100.).contains(&a.pos_x) && (0. ..50.).contains(&a.pos_y)));
+        dish.agents
+    };
+    let dist = |a: &rust_webpack_template::sim::Agent| (a.pos_x - 50.).hypot(a.pos_y - 25.);
+
+    // radii are fractions of the shorter side: 0.2 of 50 is 10 cells
+    for a in spawn(SpawnPattern::Ring { x: 0.5, y: 0.5, radius: 0.2, facing: Facing::Inward }) {
+        assert!((dist(&a) - 10.).abs() < 1e-9);
+        let (to_x, to_y) = (50. - a.pos_x, 25. - a.pos_y);
+        assert!((a.heading.cos() * to_x + a.heading.sin() * to_y - 10.).abs() < 1e-9);
+    }
+    let disk = spawn(SpawnPattern::Disk { x: 0.5, y: 0.5, radius: 0.2, facing: Facing::Random });
+    assert!(disk.iter().all(|a| dist(a) <= 10.));
+    assert!(disk.iter().any(|a| dist(a) < 5.));
+    assert!(spawn(SpawnPattern::Point { x: 0.5, y: 0.5 }).iter().all(|a| (a.pos_x, a.pos_y) == (50., 25.)));
+    let blob = spawn(SpawnPattern::Gaussian { x: 0.5, y: 0.5, sigma: 0.05 });
+    assert!(blob.iter().filter(|a| dist(a) < 5.).count() > 60);
+    let uniform = spawn(SpawnPattern::Uniform);
+    assert!(uniform.iter().any(|a| a.pos_x < 50.) && uniform.iter().any(|a| a.pos_x > 50.));
+
+    let mut density = Vec2d::new(100, 50, 0f32);
+    density[(40, 80)] = 1.;
+    density[(10, 20)] = 3.;
+    let from_image = spawn(SpawnPattern::Image(density));
+    assert!(from_image.iter().all(|a| (a.pos_y.round(), a.pos_x.round()) == (40., 80.) || (a.pos_y.round(), a.pos_x.round()) == (10., 20.)));
+    assert!(from_image.iter().filter(|a| a.pos_x.round() == 20.).count() > 60);
+    let listed = spawn(SpawnPattern::List(vec![(1., 2., PI), (3., 4., 0.)]));
+    assert_eq!((listed[0].pos_x, listed[0].pos_y, listed[0].heading), (1., 2., PI));
+    assert_eq!((listed[3].pos_x, listed[3].pos_y, listed[3].heading), (3., 4., 0.));
+
+    assert!(SimParams { spawn: SpawnPattern::Image(Vec2d::new(10, 10, 1.)), ..small(100, 50) }.validate().is_err());
+    assert!(SimParams { spawn: SpawnPattern::List(vec![]), ..small(100, 50) }.validate().is_err());
+
+    let mut params = SimParams::default();
+    params.set_str("spawn", "disk").unwrap();
+    params.set_str("spawn_facing", "outward").unwrap();
+    params.set_str("spawn_radius", "0.1").unwrap();
+    assert_eq!(params.spawn, SpawnPattern::Disk { x: 0.25, y: 0.25, radius: 0.1, facing: Facing::Outward });
+    params.set_str("spawn", "point").unwrap();
+    assert!(params.set_str("spawn_facing", "inward").is_err());
+    assert!(params.set_str("spawn", "spiral").is_err());
+}
+
+#[test]
+fn reset_starts_over() {
+    use rust_webpack_template::spawn::SpawnPattern;
+
+    let fresh = Dish::new(small(64, 32), 9).unwrap();
+    let mut dish = fresh.clone();
+    for i in 0..10 {
+        dish.update(i);
+    }
+    dish.reset();
+    assert_eq!(dish.agents, fresh.agents);
+    assert!(dish.data[0].as_slice().iter().all(|&v| v == 0));
+    let mut again = fresh.clone();
+    for i in 0..10 {
+        dish.update(i);
+        again.update(i);
+    }
+    assert_eq!(dish.data, again.data);
+
+    dish.reset_with(SpawnPattern::Uniform).unwrap();
+    assert_eq!(dish.agents, Dish::new(SimParams { spawn: SpawnPattern::Uniform, ..small(64, 32) }, 9).unwrap().agents);
+    assert!(dish.reset_with(SpawnPattern::Gaussian { x: 0.5, y: 0.5, sigma: 0. }).is_err());
+}
+
+#[test]
+fn reset_keeps_the_set_repellent() {
+    use rust_webpack_template::brush::{ Brush, Tool };
+    use rust_webpack_template::sim::Source;
+
+    let mut params = SimParams { boundary: Boundary::Reflective, ..small(32, 32) };
+    params.decay = Decay::Multiplicative { factor: 0.5 };
+    let mut dish = Dish::new(params, 0).unwrap();
+    let mut hazard = Vec2d::new(32, 32, 0f32);
+    hazard[(4, 4)] = 8.;
+    hazard[(20, 20)] = 8.;
+    dish.set_repellent(Some(hazard.clone())).unwrap();
+    let mut walls = Vec2d::new(32, 32, false);
+    walls[(20, 20)] = true;
+    dish.set_walls(Some(walls)).unwrap();
+    dish.sources.push(Source { pos_x: 16., pos_y: 16., strength: 0., repellent: 9., radius: 0., ..Source::default() });
+    dish.paint(&Brush { tool: Tool::Repellent, radius: 1., ..Brush::default() }, 10., 10.).unwrap();
+    for i in 0..5 {
+        dish.update(i);
+    }
+    assert_ne!(dish.repellent.as_ref(), Some(&hazard));
+
+    // spread, emissions and painting are dropped, and walls still clear what's under them
+    dish.reset();
+    hazard[(20, 20)] = 0.;
+    assert_eq!(dish.repellent.as_ref(), Some(&hazard));
+    dish.set_repellent(None).unwrap();
+    dish.reset();
+    assert!(dish.repellent.is_none());
+}
+
+#[test]
+fn vec2d_resize() {
+    use rust_webpack_template::vec2d::Resize;
+
+    let mut v = Vec2d::new(2, 2, 0u8);
+    v.as_mut_slice().copy_from_slice(&[10, 20, 30, 40]);
+    assert_eq!(v.crop(3, 1, 7).as_slice(), &[10, 20, 7]);
+    assert_eq!(v.rescale_nearest(4, 2).as_slice(), &[10, 10, 20, 20, 30, 30, 40, 40]);
+    let mut wide = v.clone();
+    wide.resize(4, 1, Resize::Bilinear);
+    // row centers land halfway between the old rows; columns 1 and 2 a quarter of the way in
+    assert_eq!(wide.as_slice(), &[20, 23, 28, 30]);
+    let mut same = v.clone();
+    same.resize(2, 2, Resize::Bilinear);
+    assert_eq!(same, v);
+
+    assert_eq!(Resize::Crop.remap(5., 10, 20), 5.);
+    assert_eq!(Resize::Nearest.remap(4.5, 10, 20), 9.5);
+}
+
+#[test]
+fn dish_resize() {
+    use rust_webpack_template::vec2d::Resize;
+
+    let mut dish = Dish::new(small(64, 32), 2).unwrap();
+    for i in 0..20 {
+        dish.update(i);
+    }
+    let before = dish.clone();
+    let mut walls = Vec2d::new(64, 32, false);
+    walls[(0, 63)] = true;
+    dish.set_walls(Some(walls)).unwrap();
+
+    let mut doubled = dish.clone();
+    doubled.resize(128, 64, Resize::Nearest).unwrap();
+    assert_eq!((doubled.size_w, doubled.size_h, doubled.params.size_w), (128, 64, 128));
+    assert_eq!(doubled.data[0][(20, 30)], dish.data[0][(10, 15)]);
+    assert_eq!(doubled.data[0][(21, 31)], dish.data[0][(10, 15)]);
+    assert!(doubled.walls.as_ref().unwrap()[(1, 127)]);
+    for (a, b) in doubled.agents.iter().zip(&dish.agents) {
+        // past the last cell center wraps around on the torus
+        assert!((a.pos_x - (b.pos_x * 2. + 0.5).rem_euclid(128.)).abs() < 1e-9);
+        assert!((a.pos_y - (b.pos_y * 2. + 0.5).rem_euclid(64.)).abs() < 1e-9);
+    }
+    let mut dense = doubled.clone();
+    doubled.diffuse();
+    dense.diffuse_nsquared();
+    assert_eq!(doubled.data, dense.data);
+    for i in 0..5 {
+        doubled.update(i);
+    }
+
+    let mut smooth = before.clone();
+    smooth.resize(96, 48, Resize::Bilinear).unwrap();
+    assert!(smooth.data[0].as_slice().iter().any(|&v| v > 0));
+    assert!(smooth.agents.iter().all(|a| (0. ..96.).contains(&a.pos_x) && (0. ..48.).contains(&a.pos_y)));
+
+    let mut cropped = before.clone();
+    cropped.resize(16, 40, Resize::Crop).unwrap();
+    assert_eq!(cropped.data[0][(5, 5)], before.data[0][(5, 5)]);
+    assert_eq!(cropped.data[0][(35, 5)], 0);
+    assert!(cropped.agents.iter().all(|a| (0. ..16.).contains(&a.pos_x) && (0. ..40.).contains(&a.pos_y)));
+
+    // too small for the kernel: nothing changes
+    let mut tiny = before.clone();
+    assert!(tiny.resize(2, 2, Resize::Nearest).is_err());
+    assert_eq!((tiny.size_w, tiny.data[0].size_w), (64, 64));
+}
+
+#[test]
+fn brush_paints_fields() {
+    use rust_webpack_template::brush::{ Brush, Tool };
+
+    let mut params = SimParams { boundary: Boundary::Reflective, ..SimParams::with_species(2) };
+    params.size_w = 32;
+    params.size_h = 24;
+    params.set("num_agents", 0.).unwrap();
+    let mut dish = Dish::<f32>::with_trail(params, 0).unwrap();
+    // a radius of 1.5 covers the 3x3 square around (x, y) = (10, 20)
+    let mut brush = Brush { radius: 1.5, strength: 30., species: Some(1), ..Brush::default() };
+    dish.paint(&brush, 10.2, 19.9).unwrap();
+    assert_eq!(dish.data[1][(21, 11)], 30.);
+    assert_eq!(dish.data[1][(22, 10)], 0.);
+    assert_eq!(dish.data[1].as_slice().iter().sum::<f32>(), 270.);
+    assert!(dish.data[0].as_slice().iter().all(|&v| v == 0.));
+    // painted trail spreads like deposits do
+    dish.update(0);
+    assert!(dish.data[1][(22, 10)] > 0.);
+
+    brush.erase = true;
+    brush.radius = 6.;
+    brush.strength = 1000.;
+    dish.paint(&brush, 10., 20.).unwrap();
+    assert!(dish.data[1].as_slice().iter().all(|&v| v == 0.));
+
+    // cells past the edge fold back in rather than getting painted twice
+    brush = Brush { tool: Tool::Nutrient, radius: 1., strength: 2., ..Brush::default() };
+    dish.paint(&brush, 0., 0.).unwrap();
+    let nutrient = dish.nutrient.as_ref().unwrap();
+    assert_eq!([nutrient[(0, 0)], nutrient[(0, 1)], nutrient[(1, 0)], nutrient[(1, 1)]], [2., 2., 2., 0.]);
+    brush.tool = Tool::Repellent;
+    dish.paint_line(&brush, (5., 5.), (15., 5.)).unwrap();
+    let repellent = dish.repellent.as_ref().unwrap();
+    assert!((6..=15).all(|x| repellent[(5, x)] > 0.));
+    assert_eq!(repellent[(5, 3)], 0.);
+
+    assert!(dish.paint(&Brush { species: Some(2), ..Brush::default() }, 1., 1.).is_err());
+    assert!(dish.paint(&Brush { radius: -1., ..Brush::default() }, 1., 1.).is_err());
+    assert!(dish.paint(&Brush::default(), f64::NAN, 1.).is_err());
+}
+
+#[test]
+fn brush_paints_walls_and_agents() {
+    use rust_webpack_template::brush::{ Brush, Tool };
+
+    let mut params = small(32, 24);
+    params.species[0].num_agents = 0;
+    let mut dish = Dish::<f32>::with_trail(params, 0).unwrap();
+    dish.paint(&Brush { radius: 3., ..Brush::default() }, 8., 8.).unwrap();
+    let wall = Brush { tool: Tool::Wall, radius: 1., ..Brush::default() };
+    dish.paint(&wall, 8., 8.).unwrap();
+    let walls = dish.walls.as_ref().unwrap();
+    assert_eq!(walls.as_slice().iter().filter(|&&w| w).count(), 5);
+    // walls clear the trail under them, and nothing paints into them
+    assert_eq!(dish.data[0][(8, 8)], 0.);
+    assert!(dish.data[0][(8, 10)] > 0.);
+    dish.paint(&Brush::default(), 8., 8.).unwrap();
+    assert_eq!(dish.data[0][(8, 8)], 0.);
+    dish.paint(&Brush { erase: true, ..wall.clone() }, 8., 9.).unwrap();
+    assert_eq!(dish.walls.as_ref().unwrap().as_slice().iter().filter(|&&w| w).count(), 3);
+
+    let agents = Brush { tool: Tool::Agents, radius: 3., strength: 50., ..Brush::default() };
+    dish.paint(&agents, 20., 12.).unwrap();
+    assert_eq!(dish.agents.len(), 50);
+    assert!(dish.agents.iter().all(|a| (a.pos_x - 20.).hypot(a.pos_y - 12.) <= 3.));
+    assert!(dish.paint(&Brush { strength: 1e9, ..agents.clone() }, 20., 12.).is_err());
+    assert!(dish.paint(&Brush { radius: 1e4, ..Brush::default() }, 20., 12.).is_err());
+    dish.paint(&Brush { radius: 40., strength: 0., ..Brush::default() }, 20., 12.).unwrap();
+    assert_eq!(dish.agents.len(), 50);
+    // a dab on the last wall cell spawns none inside it
+    dish.paint(&Brush { radius: 0., ..agents.clone() }, 8., 7.).unwrap();
+    assert_eq!(dish.agents.len(), 50);
+
+    dish.paint(&Brush { erase: true, radius: 1.5, ..agents.clone() }, 20., 12.).unwrap();
+    let left = dish.agents.len();
+    assert!(left < 50 && left > 0);
+    assert!(dish.agents.iter().all(|a| (a.pos_x.round() - 20.).abs() > 1. || (a.pos_y.round() - 12.).abs() > 1.));
+    dish.paint(&Brush { erase: true, radius: 10., ..agents }, 20., 12.).unwrap();
+    assert!(dish.agents.is_empty());
+
+    // a wall painted over agents sweeps away those it covers
+    dish.paint(&Brush { radius: 3., ..agents.clone() }, 20., 12.).unwrap();
+    dish.paint(&Brush { radius: 3., ..agents }, 5., 20.).unwrap();
+    dish.paint(&Brush { radius: 4., ..wall }, 20., 12.).unwrap();
+    assert_eq!(dish.agents.len(), 50);
+    assert!(dish.agents.iter().all(|a| (a.pos_x - 5.).hypot(a.pos_y - 20.) <= 3.));
+}
@@ -0,0 +1,67 @@
+use rust_webpack_template::params::SimParams;
+use rust_webpack_template::sim::Dish;
+use rust_webpack_template::snapshot::{ self, VERSION };
+
+mod common;
+use common::started;
+
+fn assert_same_future(mut a: Dish, mut b: Dish) {
+    for i in 0..15 {
+        a.update(i);
+        b.update(i);
+    }
+    assert_eq!(a.data, b.data);
+    for (x, y) in a.agents.iter().zip(&b.agents) {
+        assert_eq!(x.pos_x.to_bits(), y.pos_x.to_bits());
+        assert_eq!(x.pos_y.to_bits(), y.pos_y.to_bits());
+        assert_eq!(x.heading.to_bits(), y.heading.to_bits());
+    }
+}
+
+#[test]
+fn binary_round_trip_resumes_exactly() {
+    let dish = started();
+    let restored = snapshot::load(&snapshot::save(&dish)).unwrap();
+    assert_eq!(restored.params, dish.params);
+    assert_eq!(restored.seed, dish.seed);
+    assert_same_future(dish, restored);
+}
+
+#[test]
+fn json_round_trip_resumes_exactly() {
+    let dish = started();
+    let restored = snapshot::load_json(&snapshot::save_json(&dish)).unwrap();
+    assert_same_future(dish, restored);
+}
+
+#[test]
+fn rejects_bad_snapshots() {
+    let mut bytes = snapshot::save(&started());
+    assert!(snapshot::load::<u8>(&bytes[..3]).is_err());
+    assert!(snapshot::load::<u8>(&bytes[..bytes.len() - 5]).is_err());
+
+    bytes[4] = 99;
+    assert!(snapshot::load::<u8>(&bytes).unwrap_err().contains("version"));
+    bytes[0] = b'X';
+    assert!(snapshot::load::<u8>(&bytes).is_err());
+
+    let json = snapshot::save_json(&started())
+        .replacen(&format!("\"version\": {}", VERSION), &format!("\"version\": {}", VERSION + 1), 1);
+    assert!(snapshot::load_json::<u8>(&json).is_err());
+}
+
+#[test]
+fn trail_type_is_checked() {
+    let dish = started();
+    assert!(snapshot::load::<f32>(&snapshot::save(&dish)).unwrap_err().contains("u8"));
+    assert!(snapshot::load_json::<u16>(&snapshot::save_json(&dish)).is_err());
+
+    let mut params = SimParams { size_w: 32, size_h: 32, ..SimParams::default() };
+    params.set("num_agents", 20.).unwrap();
+    let mut float = Dish::<f32>::with_trail(params, 1).unwrap();
+    for i in 0..5 {
+        float.update(i);
+    }
+    let restored: Dish<f32> = snapshot::load(&snapshot::save(&float)).unwrap();
+    assert_eq!(restored.data, float.data);
+}